@@ -0,0 +1,1121 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde_json::{Map, Number, Value};
+
+use crate::compiler::{
+    Binding, CompileDiagnostic, CompilePlan, CompiledBranch, CompiledPredicate, RelatedLocation,
+    Severity,
+};
+use crate::decision::{
+    DecisionCandidate, DecisionCase, DecisionNode, DecisionTest, DispatchCase, DispatchTable,
+};
+
+// Layout: `MAGIC`, `FORMAT_VERSION`, the string table, the value table, then the plan.
+// Integers are unsigned LEB128, floats are little-endian f64, and strings and JSON
+// values are indices into their tables, so each distinct one is stored once.
+const MAGIC: &[u8; 4] = b"MPLN";
+const FORMAT_VERSION: u8 = 2;
+// Nesting limit for predicates, decision nodes and JSON values while decoding.
+const MAX_DEPTH: usize = 256;
+// Value entries share earlier entries by index, so decoding expands them; this caps the
+// total number of JSON nodes built per input byte.
+const MAX_VALUE_NODES_PER_BYTE: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    pub message: String,
+    pub offset: usize,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "byte {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+pub fn encode_plan(plan: &CompilePlan) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    encoder.plan(plan);
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(FORMAT_VERSION);
+    write_varint(&mut bytes, encoder.strings.len() as u64);
+    for string in &encoder.strings {
+        write_varint(&mut bytes, string.len() as u64);
+        bytes.extend_from_slice(string.as_bytes());
+    }
+    write_varint(&mut bytes, encoder.value_count as u64);
+    bytes.extend_from_slice(&encoder.values);
+    bytes.extend_from_slice(&encoder.body);
+    bytes
+}
+
+pub fn decode_plan(bytes: &[u8]) -> Result<CompilePlan, DecodeError> {
+    let mut decoder = Decoder {
+        bytes,
+        offset: 0,
+        strings: Vec::new(),
+        values: Vec::new(),
+        value_shapes: Vec::new(),
+        value_nodes: 0,
+        branch_count: 0,
+    };
+    if !bytes.starts_with(MAGIC) {
+        return Err(decoder.error("not an encoded compile plan"));
+    }
+    decoder.offset = MAGIC.len();
+    let format_version = decoder.byte()?;
+    if format_version != FORMAT_VERSION {
+        decoder.offset -= 1;
+        return Err(decoder.error(&format!("unsupported encoding version {}", format_version)));
+    }
+
+    for _ in 0..decoder.length()? {
+        let length = decoder.length()?;
+        let start = decoder.offset;
+        let string = std::str::from_utf8(decoder.take(length)?)
+            .map(str::to_string)
+            .map_err(|_| DecodeError {
+                message: "invalid UTF-8 in string table".to_string(),
+                offset: start,
+            })?;
+        decoder.strings.push(string);
+    }
+    for _ in 0..decoder.length()? {
+        decoder.value_entry()?;
+    }
+
+    let plan = decoder.plan()?;
+    if decoder.offset != bytes.len() {
+        return Err(decoder.error("unexpected trailing bytes"));
+    }
+    Ok(plan)
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut number: u64) {
+    while number >= 0x80 {
+        bytes.push((number as u8) | 0x80);
+        number >>= 7;
+    }
+    bytes.push(number as u8);
+}
+
+// Tags of the value table entries.
+const VALUE_NULL: u8 = 0;
+const VALUE_FALSE: u8 = 1;
+const VALUE_TRUE: u8 = 2;
+const VALUE_UNSIGNED: u8 = 3;
+const VALUE_NEGATIVE: u8 = 4;
+const VALUE_FLOAT: u8 = 5;
+const VALUE_STRING: u8 = 6;
+const VALUE_ARRAY: u8 = 7;
+const VALUE_OBJECT: u8 = 8;
+
+#[derive(Debug, Default)]
+struct Encoder {
+    body: Vec<u8>,
+    strings: Vec<String>,
+    string_indices: HashMap<String, usize>,
+    // Entries are written children first, so they only refer to earlier entries.
+    values: Vec<u8>,
+    value_count: usize,
+    value_indices: HashMap<String, usize>,
+}
+
+impl Encoder {
+    fn varint(&mut self, number: usize) {
+        write_varint(&mut self.body, number as u64);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.body.push(value as u8);
+    }
+
+    fn intern_string(&mut self, string: &str) -> usize {
+        if let Some(index) = self.string_indices.get(string) {
+            return *index;
+        }
+        let index = self.strings.len();
+        self.strings.push(string.to_string());
+        self.string_indices.insert(string.to_string(), index);
+        index
+    }
+
+    // JSON text identifies a value: `1` and `1.0` stay distinct, as in `Value`.
+    fn intern_value(&mut self, value: &Value) -> usize {
+        let key = value.to_string();
+        if let Some(index) = self.value_indices.get(&key) {
+            return *index;
+        }
+
+        let mut entry = Vec::new();
+        match value {
+            Value::Null => entry.push(VALUE_NULL),
+            Value::Bool(false) => entry.push(VALUE_FALSE),
+            Value::Bool(true) => entry.push(VALUE_TRUE),
+            Value::Number(number) => {
+                if let Some(unsigned) = number.as_u64() {
+                    entry.push(VALUE_UNSIGNED);
+                    write_varint(&mut entry, unsigned);
+                } else if let Some(negative) = number.as_i64() {
+                    entry.push(VALUE_NEGATIVE);
+                    write_varint(&mut entry, !(negative as u64));
+                } else {
+                    entry.push(VALUE_FLOAT);
+                    let float = number.as_f64().unwrap_or_default();
+                    entry.extend_from_slice(&float.to_le_bytes());
+                }
+            }
+            Value::String(string) => {
+                entry.push(VALUE_STRING);
+                write_varint(&mut entry, self.intern_string(string) as u64);
+            }
+            Value::Array(items) => {
+                entry.push(VALUE_ARRAY);
+                write_varint(&mut entry, items.len() as u64);
+                for item in items {
+                    write_varint(&mut entry, self.intern_value(item) as u64);
+                }
+            }
+            Value::Object(map) => {
+                entry.push(VALUE_OBJECT);
+                write_varint(&mut entry, map.len() as u64);
+                for (key, item) in map {
+                    write_varint(&mut entry, self.intern_string(key) as u64);
+                    write_varint(&mut entry, self.intern_value(item) as u64);
+                }
+            }
+        }
+
+        let index = self.value_count;
+        self.values.extend_from_slice(&entry);
+        self.value_count += 1;
+        self.value_indices.insert(key, index);
+        index
+    }
+
+    fn string(&mut self, string: &str) {
+        let index = self.intern_string(string);
+        self.varint(index);
+    }
+
+    // `None` is 0, `Some(string)` is its index plus one.
+    fn optional_string(&mut self, string: Option<&str>) {
+        let index = string.map_or(0, |string| self.intern_string(string) + 1);
+        self.varint(index);
+    }
+
+    fn path(&mut self, path: &[String]) {
+        self.varint(path.len());
+        for key in path {
+            self.string(key);
+        }
+    }
+
+    fn value(&mut self, value: &Value) {
+        let index = self.intern_value(value);
+        self.varint(index);
+    }
+
+    fn optional_value(&mut self, value: Option<&Value>) {
+        let index = value.map_or(0, |value| self.intern_value(value) + 1);
+        self.varint(index);
+    }
+
+    fn indices(&mut self, indices: &[usize]) {
+        self.varint(indices.len());
+        for index in indices {
+            self.varint(*index);
+        }
+    }
+
+    fn plan(&mut self, plan: &CompilePlan) {
+        self.varint(plan.version as usize);
+        self.varint(plan.dynamic_slot_count);
+
+        self.varint(plan.branches.len());
+        for branch in &plan.branches {
+            self.varint(branch.action_index);
+            self.predicate(&branch.predicate);
+        }
+
+        self.varint(plan.diagnostics.len());
+        for diagnostic in &plan.diagnostics {
+            self.string(&diagnostic.code);
+            self.string(diagnostic.level.as_str());
+            self.string(&diagnostic.message);
+            self.varint(diagnostic.branch_index.map_or(0, |index| index + 1));
+            self.optional_value(diagnostic.counterexample.as_ref());
+            self.bool(diagnostic.counterexample_undefined);
+            self.bool(diagnostic.path.is_some());
+            if let Some(path) = &diagnostic.path {
+                self.path(path);
+            }
+            self.varint(diagnostic.shadowed_by.map_or(0, |index| index + 1));
+            self.varint(diagnostic.related.len());
+            for related in &diagnostic.related {
+                self.varint(related.branch_index);
+                self.path(&related.path);
+                self.string(&related.message);
+            }
+        }
+
+        match &plan.decision_tree {
+            Some(node) => {
+                self.bool(true);
+                self.decision_node(node);
+            }
+            None => self.bool(false),
+        }
+
+        match &plan.dispatch {
+            Some(dispatch) => {
+                self.bool(true);
+                self.string(&dispatch.key);
+                self.varint(dispatch.cases.len());
+                for case in &dispatch.cases {
+                    self.value(&case.value);
+                    self.indices(&case.branches);
+                }
+                self.indices(&dispatch.fallback);
+            }
+            None => self.bool(false),
+        }
+
+        self.varint(plan.bindings.len());
+        for binding in &plan.bindings {
+            self.varint(binding.branch_index);
+            self.string(&binding.name);
+            self.path(&binding.path);
+        }
+    }
+
+    fn decision_node(&mut self, node: &DecisionNode) {
+        match node {
+            DecisionNode::Switch {
+                test,
+                cases,
+                default,
+            } => {
+                self.body.push(0);
+                match test {
+                    DecisionTest::Tag { key } => {
+                        self.body.push(0);
+                        self.string(key);
+                    }
+                    DecisionTest::TypeOf => self.body.push(1),
+                    DecisionTest::IsNull => self.body.push(2),
+                }
+                self.varint(cases.len());
+                for case in cases {
+                    self.value(&case.value);
+                    self.decision_node(&case.node);
+                }
+                self.decision_node(default);
+            }
+            DecisionNode::Leaf { candidates } => {
+                self.body.push(1);
+                self.varint(candidates.len());
+                for candidate in candidates {
+                    self.varint(candidate.branch_index);
+                    self.predicate(&candidate.predicate);
+                }
+            }
+        }
+    }
+
+    fn predicates(&mut self, predicates: &[CompiledPredicate]) {
+        self.varint(predicates.len());
+        for predicate in predicates {
+            self.predicate(predicate);
+        }
+    }
+
+    fn predicate(&mut self, predicate: &CompiledPredicate) {
+        self.body.push(predicate_tag(predicate));
+        match predicate {
+            CompiledPredicate::TypeOf { value }
+            | CompiledPredicate::StartsWith { value }
+            | CompiledPredicate::EndsWith { value }
+            | CompiledPredicate::Contains { value } => self.string(value),
+            CompiledPredicate::IsNull
+            | CompiledPredicate::IsUndefined
+            | CompiledPredicate::True => {}
+            CompiledPredicate::Eq { value } => self.value(value),
+            CompiledPredicate::TagEq { key, value } => {
+                self.string(key);
+                self.value(value);
+            }
+            CompiledPredicate::OneOf { values } => {
+                self.varint(values.len());
+                for value in values {
+                    self.value(value);
+                }
+            }
+            CompiledPredicate::Regex { pattern, flags } => {
+                self.string(pattern);
+                self.optional_string(flags.as_deref());
+            }
+            CompiledPredicate::Range {
+                min,
+                max,
+                min_inclusive,
+                max_inclusive,
+            } => {
+                let flags = min.is_some() as u8
+                    | (max.is_some() as u8) << 1
+                    | (*min_inclusive as u8) << 2
+                    | (*max_inclusive as u8) << 3;
+                self.body.push(flags);
+                for bound in [min, max].into_iter().flatten() {
+                    self.body.extend_from_slice(&bound.to_le_bytes());
+                }
+            }
+            CompiledPredicate::Shape { fields, exact } => {
+                self.varint(fields.len());
+                for (key, node) in fields {
+                    self.string(key);
+                    self.predicate(node);
+                }
+                self.bool(*exact);
+            }
+            CompiledPredicate::Tuple { items, rest } => {
+                self.predicates(items);
+                match rest {
+                    Some(rest) => {
+                        self.bool(true);
+                        self.predicate(rest);
+                    }
+                    None => self.bool(false),
+                }
+            }
+            CompiledPredicate::ArrayOf { predicate } | CompiledPredicate::Not { predicate } => {
+                self.predicate(predicate)
+            }
+            CompiledPredicate::ArrayLength { length } => self.varint(*length),
+            CompiledPredicate::And { predicates } | CompiledPredicate::Or { predicates } => {
+                self.predicates(predicates)
+            }
+            CompiledPredicate::Slot { slot } => self.varint(*slot as usize),
+        }
+    }
+}
+
+fn predicate_tag(predicate: &CompiledPredicate) -> u8 {
+    match predicate {
+        CompiledPredicate::TypeOf { .. } => 0,
+        CompiledPredicate::IsNull => 1,
+        CompiledPredicate::IsUndefined => 2,
+        CompiledPredicate::Eq { .. } => 3,
+        CompiledPredicate::TagEq { .. } => 4,
+        CompiledPredicate::OneOf { .. } => 5,
+        CompiledPredicate::StartsWith { .. } => 6,
+        CompiledPredicate::EndsWith { .. } => 7,
+        CompiledPredicate::Contains { .. } => 8,
+        CompiledPredicate::Regex { .. } => 9,
+        CompiledPredicate::Range { .. } => 10,
+        CompiledPredicate::Shape { .. } => 11,
+        CompiledPredicate::Tuple { .. } => 12,
+        CompiledPredicate::ArrayOf { .. } => 13,
+        CompiledPredicate::ArrayLength { .. } => 14,
+        CompiledPredicate::And { .. } => 15,
+        CompiledPredicate::Or { .. } => 16,
+        CompiledPredicate::Not { .. } => 17,
+        CompiledPredicate::Slot { .. } => 18,
+        CompiledPredicate::True => 19,
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    strings: Vec<String>,
+    values: Vec<Value>,
+    // `(nodes, depth)` of each entry in `values`.
+    value_shapes: Vec<(usize, usize)>,
+    value_nodes: usize,
+    // Known once the plan's branches are decoded.
+    branch_count: usize,
+}
+
+impl Decoder<'_> {
+    fn error(&self, message: &str) -> DecodeError {
+        DecodeError {
+            message: message.to_string(),
+            offset: self.offset,
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self
+            .bytes
+            .get(self.offset)
+            .ok_or_else(|| self.error("unexpected end of input"))?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, length: usize) -> Result<&[u8], DecodeError> {
+        if self.bytes.len() - self.offset < length {
+            return Err(self.error("unexpected end of input"));
+        }
+        let slice = &self.bytes[self.offset..self.offset + length];
+        self.offset += length;
+        Ok(slice)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let start = self.offset;
+        let mut number = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            number |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(number);
+            }
+        }
+        self.offset = start;
+        Err(self.error("integer is too large"))
+    }
+
+    fn usize(&mut self) -> Result<usize, DecodeError> {
+        let start = self.offset;
+        let number = self.varint()?;
+        usize::try_from(number).map_err(|_| DecodeError {
+            message: "integer is too large".to_string(),
+            offset: start,
+        })
+    }
+
+    // A count of items that each take at least one byte, so it cannot exceed the input.
+    fn length(&mut self) -> Result<usize, DecodeError> {
+        let length = self.usize()?;
+        if length > self.bytes.len() - self.offset {
+            return Err(self.error("length exceeds input"));
+        }
+        Ok(length)
+    }
+
+    fn bool(&mut self) -> Result<bool, DecodeError> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => {
+                self.offset -= 1;
+                Err(self.error("invalid boolean"))
+            }
+        }
+    }
+
+    fn f64(&mut self) -> Result<f64, DecodeError> {
+        let bytes = self.take(8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().unwrap_or_default()))
+    }
+
+    fn index(&mut self, length: usize, table: &str) -> Result<usize, DecodeError> {
+        let start = self.offset;
+        let index = self.usize()?;
+        if index < length {
+            Ok(index)
+        } else {
+            Err(DecodeError {
+                message: format!("{} index {} out of range", table, index),
+                offset: start,
+            })
+        }
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let index = self.index(self.strings.len(), "string")?;
+        Ok(self.strings[index].clone())
+    }
+
+    fn severity(&mut self) -> Result<Severity, DecodeError> {
+        match self.string()?.as_str() {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(self.error("invalid diagnostic level")),
+        }
+    }
+
+    fn optional_string(&mut self) -> Result<Option<String>, DecodeError> {
+        let index = self.index(self.strings.len() + 1, "string")?;
+        Ok(index
+            .checked_sub(1)
+            .map(|index| self.strings[index].clone()))
+    }
+
+    fn path(&mut self) -> Result<Vec<String>, DecodeError> {
+        (0..self.length()?).map(|_| self.string()).collect()
+    }
+
+    fn value(&mut self) -> Result<Value, DecodeError> {
+        let index = self.index(self.values.len(), "value")?;
+        self.charge(self.value_shapes[index].0)?;
+        Ok(self.values[index].clone())
+    }
+
+    fn optional_value(&mut self) -> Result<Option<Value>, DecodeError> {
+        match self.index(self.values.len() + 1, "value")?.checked_sub(1) {
+            Some(index) => {
+                self.charge(self.value_shapes[index].0)?;
+                Ok(Some(self.values[index].clone()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Counts JSON nodes about to be built against the input's budget.
+    fn charge(&mut self, nodes: usize) -> Result<(), DecodeError> {
+        self.value_nodes = self.value_nodes.saturating_add(nodes);
+        if self.value_nodes > self.bytes.len().saturating_mul(MAX_VALUE_NODES_PER_BYTE) {
+            return Err(self.error("values are too large"));
+        }
+        Ok(())
+    }
+
+    // Branch references are checked here so evaluating a decoded plan cannot index
+    // past `branches`.
+    fn branch_index(&mut self) -> Result<usize, DecodeError> {
+        self.index(self.branch_count, "branch")
+    }
+
+    fn optional_branch_index(&mut self) -> Result<Option<usize>, DecodeError> {
+        Ok(self.index(self.branch_count + 1, "branch")?.checked_sub(1))
+    }
+
+    fn branch_indices(&mut self) -> Result<Vec<usize>, DecodeError> {
+        (0..self.length()?).map(|_| self.branch_index()).collect()
+    }
+
+    // Children refer to earlier entries only, so `self.values` already holds them. Their
+    // indices are read before anything is cloned, so the size and depth checks come first.
+    fn value_entry(&mut self) -> Result<(), DecodeError> {
+        let start = self.offset;
+        let value = match self.byte()? {
+            VALUE_NULL => Value::Null,
+            VALUE_FALSE => Value::Bool(false),
+            VALUE_TRUE => Value::Bool(true),
+            VALUE_UNSIGNED => Value::from(self.varint()?),
+            VALUE_NEGATIVE => Value::from(!self.varint()? as i64),
+            VALUE_FLOAT => {
+                let float = self.f64()?;
+                Value::Number(Number::from_f64(float).ok_or(DecodeError {
+                    message: "non-finite number".to_string(),
+                    offset: start,
+                })?)
+            }
+            VALUE_STRING => Value::String(self.string()?),
+            VALUE_ARRAY => {
+                let children = (0..self.length()?)
+                    .map(|_| self.index(self.values.len(), "value"))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.check_entry(start, &children)?;
+                Value::Array(
+                    children
+                        .into_iter()
+                        .map(|index| self.values[index].clone())
+                        .collect(),
+                )
+            }
+            VALUE_OBJECT => {
+                let entries = (0..self.length()?)
+                    .map(|_| Ok((self.string()?, self.index(self.values.len(), "value")?)))
+                    .collect::<Result<Vec<_>, DecodeError>>()?;
+                let children: Vec<usize> = entries.iter().map(|(_, index)| *index).collect();
+                self.check_entry(start, &children)?;
+                Value::Object(
+                    entries
+                        .into_iter()
+                        .map(|(key, index)| (key, self.values[index].clone()))
+                        .collect::<Map<_, _>>(),
+                )
+            }
+            _ => {
+                self.offset = start;
+                return Err(self.error("invalid value tag"));
+            }
+        };
+        if !matches!(value, Value::Array(_) | Value::Object(_)) {
+            self.charge(1)?;
+            self.value_shapes.push((1, 1));
+        }
+        self.values.push(value);
+        Ok(())
+    }
+
+    // Records the shape of a container entry, failing before its children are cloned.
+    fn check_entry(&mut self, start: usize, children: &[usize]) -> Result<(), DecodeError> {
+        let (nodes, depth) = children.iter().fold((1usize, 1), |(nodes, depth), index| {
+            let (child_nodes, child_depth) = self.value_shapes[*index];
+            (
+                nodes.saturating_add(child_nodes),
+                depth.max(child_depth + 1),
+            )
+        });
+        if depth > MAX_DEPTH {
+            self.offset = start;
+            return Err(self.error("nesting is too deep"));
+        }
+        self.charge(nodes).map_err(|err| DecodeError {
+            offset: start,
+            ..err
+        })?;
+        self.value_shapes.push((nodes, depth));
+        Ok(())
+    }
+
+    fn plan(&mut self) -> Result<CompilePlan, DecodeError> {
+        let start = self.offset;
+        let version = u32::try_from(self.varint()?).map_err(|_| DecodeError {
+            message: "plan version is too large".to_string(),
+            offset: start,
+        })?;
+        let dynamic_slot_count = self.usize()?;
+
+        let branches: Vec<CompiledBranch> = (0..self.length()?)
+            .map(|_| {
+                Ok(CompiledBranch {
+                    action_index: self.usize()?,
+                    predicate: self.predicate(0)?,
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?;
+        self.branch_count = branches.len();
+
+        let diagnostics = (0..self.length()?)
+            .map(|_| {
+                Ok(CompileDiagnostic {
+                    code: self.string()?,
+                    level: self.severity()?,
+                    message: self.string()?,
+                    branch_index: self.optional_branch_index()?,
+                    counterexample: self.optional_value()?,
+                    counterexample_undefined: self.bool()?,
+                    path: if self.bool()? {
+                        Some(self.path()?)
+                    } else {
+                        None
+                    },
+                    shadowed_by: self.optional_branch_index()?,
+                    related: (0..self.length()?)
+                        .map(|_| {
+                            Ok(RelatedLocation {
+                                branch_index: self.branch_index()?,
+                                path: self.path()?,
+                                message: self.string()?,
+                            })
+                        })
+                        .collect::<Result<_, DecodeError>>()?,
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?;
+
+        let decision_tree = if self.bool()? {
+            Some(self.decision_node(0)?)
+        } else {
+            None
+        };
+
+        let dispatch = if self.bool()? {
+            let key = self.string()?;
+            let cases = (0..self.length()?)
+                .map(|_| {
+                    Ok(DispatchCase {
+                        value: self.value()?,
+                        branches: self.branch_indices()?,
+                    })
+                })
+                .collect::<Result<_, DecodeError>>()?;
+            Some(DispatchTable {
+                key,
+                cases,
+                fallback: self.branch_indices()?,
+            })
+        } else {
+            None
+        };
+
+        let bindings = (0..self.length()?)
+            .map(|_| {
+                Ok(Binding {
+                    branch_index: self.branch_index()?,
+                    name: self.string()?,
+                    path: self.path()?,
+                })
+            })
+            .collect::<Result<_, DecodeError>>()?;
+
+        Ok(CompilePlan {
+            version,
+            branches,
+            diagnostics,
+            dynamic_slot_count,
+            decision_tree,
+            dispatch,
+            bindings,
+        })
+    }
+
+    fn decision_node(&mut self, depth: usize) -> Result<DecisionNode, DecodeError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nesting is too deep"));
+        }
+        let start = self.offset;
+        match self.byte()? {
+            0 => {
+                let test = match self.byte()? {
+                    0 => DecisionTest::Tag {
+                        key: self.string()?,
+                    },
+                    1 => DecisionTest::TypeOf,
+                    2 => DecisionTest::IsNull,
+                    _ => {
+                        self.offset -= 1;
+                        return Err(self.error("invalid decision test"));
+                    }
+                };
+                let cases = (0..self.length()?)
+                    .map(|_| {
+                        Ok(DecisionCase {
+                            value: self.value()?,
+                            node: self.decision_node(depth + 1)?,
+                        })
+                    })
+                    .collect::<Result<_, DecodeError>>()?;
+                Ok(DecisionNode::Switch {
+                    test,
+                    cases,
+                    default: Box::new(self.decision_node(depth + 1)?),
+                })
+            }
+            1 => Ok(DecisionNode::Leaf {
+                candidates: (0..self.length()?)
+                    .map(|_| {
+                        Ok(DecisionCandidate {
+                            branch_index: self.branch_index()?,
+                            predicate: self.predicate(depth + 1)?,
+                        })
+                    })
+                    .collect::<Result<_, DecodeError>>()?,
+            }),
+            _ => {
+                self.offset = start;
+                Err(self.error("invalid decision node"))
+            }
+        }
+    }
+
+    fn predicates(&mut self, depth: usize) -> Result<Vec<CompiledPredicate>, DecodeError> {
+        (0..self.length()?)
+            .map(|_| self.predicate(depth + 1))
+            .collect()
+    }
+
+    fn predicate(&mut self, depth: usize) -> Result<CompiledPredicate, DecodeError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nesting is too deep"));
+        }
+        let start = self.offset;
+        let predicate = match self.byte()? {
+            0 => CompiledPredicate::TypeOf {
+                value: self.string()?,
+            },
+            1 => CompiledPredicate::IsNull,
+            2 => CompiledPredicate::IsUndefined,
+            3 => CompiledPredicate::Eq {
+                value: self.value()?,
+            },
+            4 => CompiledPredicate::TagEq {
+                key: self.string()?,
+                value: self.value()?,
+            },
+            5 => CompiledPredicate::OneOf {
+                values: (0..self.length()?)
+                    .map(|_| self.value())
+                    .collect::<Result<_, _>>()?,
+            },
+            6 => CompiledPredicate::StartsWith {
+                value: self.string()?,
+            },
+            7 => CompiledPredicate::EndsWith {
+                value: self.string()?,
+            },
+            8 => CompiledPredicate::Contains {
+                value: self.string()?,
+            },
+            9 => CompiledPredicate::Regex {
+                pattern: self.string()?,
+                flags: self.optional_string()?,
+            },
+            10 => {
+                let flags = self.byte()?;
+                if flags > 0b1111 {
+                    self.offset -= 1;
+                    return Err(self.error("invalid range flags"));
+                }
+                let min = if flags & 1 != 0 {
+                    Some(self.f64()?)
+                } else {
+                    None
+                };
+                let max = if flags & 2 != 0 {
+                    Some(self.f64()?)
+                } else {
+                    None
+                };
+                CompiledPredicate::Range {
+                    min,
+                    max,
+                    min_inclusive: flags & 4 != 0,
+                    max_inclusive: flags & 8 != 0,
+                }
+            }
+            11 => {
+                let mut fields = BTreeMap::new();
+                for _ in 0..self.length()? {
+                    let key = self.string()?;
+                    fields.insert(key, self.predicate(depth + 1)?);
+                }
+                CompiledPredicate::Shape {
+                    fields,
+                    exact: self.bool()?,
+                }
+            }
+            12 => CompiledPredicate::Tuple {
+                items: self.predicates(depth)?,
+                rest: if self.bool()? {
+                    Some(Box::new(self.predicate(depth + 1)?))
+                } else {
+                    None
+                },
+            },
+            13 => CompiledPredicate::ArrayOf {
+                predicate: Box::new(self.predicate(depth + 1)?),
+            },
+            14 => CompiledPredicate::ArrayLength {
+                length: self.usize()?,
+            },
+            15 => CompiledPredicate::And {
+                predicates: self.predicates(depth)?,
+            },
+            16 => CompiledPredicate::Or {
+                predicates: self.predicates(depth)?,
+            },
+            17 => CompiledPredicate::Not {
+                predicate: Box::new(self.predicate(depth + 1)?),
+            },
+            18 => {
+                let start = self.offset;
+                CompiledPredicate::Slot {
+                    slot: u32::try_from(self.varint()?).map_err(|_| DecodeError {
+                        message: "slot is too large".to_string(),
+                        offset: start,
+                    })?,
+                }
+            }
+            19 => CompiledPredicate::True,
+            _ => {
+                self.offset = start;
+                return Err(self.error("invalid predicate tag"));
+            }
+        };
+        Ok(predicate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{
+        CompileOptions, MatchProgram, PredicateAst, compile_program_with_options, tests::branch,
+    };
+    use serde_json::json;
+
+    fn tagged(value: &str, predicate: PredicateAst) -> PredicateAst {
+        PredicateAst::And {
+            predicates: vec![
+                PredicateAst::Tag {
+                    key: "type".to_string(),
+                    value: json!(value),
+                },
+                predicate,
+            ],
+        }
+    }
+
+    #[test]
+    fn test_round_trips_plan() {
+        let program = MatchProgram {
+            branches: vec![
+                branch(tagged(
+                    "pay",
+                    PredicateAst::Shape {
+                        fields: BTreeMap::from([(
+                            "amount".to_string(),
+                            PredicateAst::Bind {
+                                name: "amount".to_string(),
+                                predicate: Box::new(PredicateAst::Range {
+                                    min: Some(f64::NEG_INFINITY),
+                                    max: Some(1e21),
+                                    min_inclusive: false,
+                                    max_inclusive: true,
+                                }),
+                            },
+                        )]),
+                        exact: false,
+                    },
+                )),
+                branch(tagged(
+                    "refund",
+                    PredicateAst::Or {
+                        predicates: vec![
+                            PredicateAst::Eq {
+                                value: json!({ "lines": [-1, 2.5, null, "pay"] }),
+                            },
+                            PredicateAst::Regex {
+                                pattern: "^r".to_string(),
+                                flags: Some("i".to_string()),
+                            },
+                            PredicateAst::Tuple {
+                                items: vec![PredicateAst::Slot { slot: 0 }],
+                                rest: Some(Box::new(PredicateAst::IsUndefined)),
+                            },
+                        ],
+                    },
+                )),
+                branch(tagged("refund", PredicateAst::Wildcard)),
+                branch(PredicateAst::Wildcard),
+                branch(tagged("void", PredicateAst::Wildcard)),
+            ],
+            input_schema: None,
+        };
+        let plan = compile_program_with_options(
+            &program,
+            &CompileOptions {
+                decision_tree: true,
+                dispatch: true,
+                ..CompileOptions::default()
+            },
+        );
+        assert!(plan.decision_tree.is_some() && plan.dispatch.is_some());
+        assert!(!plan.bindings.is_empty() && !plan.diagnostics.is_empty());
+        assert!(plan.diagnostics.iter().any(|item| item.path.is_some()));
+        assert!(plan.diagnostics.iter().any(|item| !item.related.is_empty()));
+
+        let bytes = encode_plan(&plan);
+        assert_eq!(decode_plan(&bytes), Ok(plan.clone()));
+        assert!(bytes.len() * 4 < serde_json::to_vec(&plan).unwrap().len());
+        let occurrences = bytes.windows(6).filter(|bytes| bytes == b"refund").count();
+        assert_eq!(occurrences, 1);
+    }
+
+    #[test]
+    fn test_rejects_invalid_input() {
+        let plan = compile_program_with_options(
+            &MatchProgram {
+                branches: vec![branch(PredicateAst::IsString)],
+                input_schema: None,
+            },
+            &CompileOptions::default(),
+        );
+        let bytes = encode_plan(&plan);
+
+        let error = decode_plan(b"{\"version\":1}").unwrap_err();
+        assert_eq!(error.to_string(), "byte 0: not an encoded compile plan");
+
+        let mut future = bytes.clone();
+        future[4] = FORMAT_VERSION + 1;
+        let error = decode_plan(&future).unwrap_err();
+        assert_eq!(error.offset, 4);
+        assert_eq!(
+            error.message,
+            format!("unsupported encoding version {}", FORMAT_VERSION + 1)
+        );
+
+        let error = decode_plan(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(error.message, "unexpected end of input");
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        let error = decode_plan(&trailing).unwrap_err();
+        assert_eq!(error.message, "unexpected trailing bytes");
+
+        let mut dangling = plan.clone();
+        dangling.dispatch = Some(DispatchTable {
+            key: "type".to_string(),
+            cases: Vec::new(),
+            fallback: vec![5],
+        });
+        let error = decode_plan(&encode_plan(&dangling)).unwrap_err();
+        assert_eq!(error.message, "branch index 5 out of range");
+    }
+
+    // Replaces the (empty) value table of a one-branch plan with `entries`.
+    fn with_values(count: usize, entries: &[u8]) -> Vec<u8> {
+        let plan = compile_program_with_options(
+            &MatchProgram {
+                branches: vec![branch(PredicateAst::IsString)],
+                input_schema: None,
+            },
+            &CompileOptions::default(),
+        );
+        let bytes = encode_plan(&plan);
+        let mut decoder = Decoder {
+            bytes: &bytes,
+            offset: MAGIC.len() + 1,
+            strings: Vec::new(),
+            values: Vec::new(),
+            value_shapes: Vec::new(),
+            value_nodes: 0,
+            branch_count: 0,
+        };
+        for _ in 0..decoder.length().unwrap() {
+            let length = decoder.length().unwrap();
+            decoder.take(length).unwrap();
+        }
+        let table = decoder.offset;
+        assert_eq!(decoder.length(), Ok(0));
+
+        let mut spliced = bytes[..table].to_vec();
+        write_varint(&mut spliced, count as u64);
+        spliced.extend_from_slice(entries);
+        spliced.extend_from_slice(&bytes[decoder.offset..]);
+        spliced
+    }
+
+    #[test]
+    fn test_rejects_exponential_values() {
+        let mut entries = vec![VALUE_NULL];
+        for level in 0..24u8 {
+            entries.extend_from_slice(&[VALUE_ARRAY, 2, level, level]);
+        }
+        let error = decode_plan(&with_values(25, &entries)).unwrap_err();
+        assert_eq!(error.message, "values are too large");
+
+        let mut entries = vec![VALUE_ARRAY, 0];
+        for level in 0..2 {
+            entries.extend_from_slice(&[VALUE_ARRAY, 2, level, level]);
+        }
+        assert!(decode_plan(&with_values(3, &entries)).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_deep_values() {
+        let chain = |levels: usize| {
+            let mut entries = vec![VALUE_NULL];
+            for level in 0..levels {
+                entries.extend_from_slice(&[VALUE_ARRAY, 1]);
+                write_varint(&mut entries, level as u64);
+            }
+            with_values(levels + 1, &entries)
+        };
+        assert!(decode_plan(&chain(MAX_DEPTH - 1)).is_ok());
+        let error = decode_plan(&chain(MAX_DEPTH)).unwrap_err();
+        assert_eq!(error.message, "nesting is too deep");
+    }
+}
@@ -0,0 +1,1485 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::decision::{DecisionNode, DispatchTable, build_decision_tree, build_dispatch_table};
+use crate::error::{MatchPatternError, deserialize};
+use crate::evaluator::{compile_regex, values_equal};
+use crate::exhaustiveness::{InputSchema, find_unhandled_input};
+use crate::ordering::order_by_cost;
+use crate::simplify::simplify_predicate;
+use crate::subsumption::{contradictions, covers};
+
+pub const COMPILE_PLAN_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MatchProgram {
+    pub branches: Vec<BranchAst>,
+    #[serde(
+        rename = "inputSchema",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub input_schema: Option<InputSchema>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BranchAst {
+    #[serde(rename = "actionIndex")]
+    pub action_index: Option<usize>,
+    pub predicate: PredicateAst,
+    // Diagnostic codes that are never reported for this branch.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PredicateAst {
+    IsNumber,
+    IsString,
+    IsBoolean,
+    IsNull,
+    IsUndefined,
+    Eq {
+        value: Value,
+    },
+    Tag {
+        key: String,
+        value: Value,
+    },
+    OneOf {
+        values: Vec<Value>,
+    },
+    StartsWith {
+        value: String,
+    },
+    EndsWith {
+        value: String,
+    },
+    Contains {
+        value: String,
+    },
+    Regex {
+        pattern: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        flags: Option<String>,
+    },
+    Range {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<f64>,
+        #[serde(rename = "minInclusive", default)]
+        min_inclusive: bool,
+        #[serde(rename = "maxInclusive", default)]
+        max_inclusive: bool,
+    },
+    Shape {
+        fields: BTreeMap<String, PredicateAst>,
+        #[serde(default)]
+        exact: bool,
+    },
+    Tuple {
+        items: Vec<PredicateAst>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rest: Option<Box<PredicateAst>>,
+    },
+    ArrayOf {
+        predicate: Box<PredicateAst>,
+    },
+    ArrayLength {
+        length: usize,
+    },
+    And {
+        predicates: Vec<PredicateAst>,
+    },
+    Or {
+        predicates: Vec<PredicateAst>,
+    },
+    Not {
+        predicate: Box<PredicateAst>,
+    },
+    Slot {
+        slot: u32,
+    },
+    Bind {
+        name: String,
+        predicate: Box<PredicateAst>,
+    },
+    Wildcard,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompilePlan {
+    pub version: u32,
+    pub branches: Vec<CompiledBranch>,
+    pub diagnostics: Vec<CompileDiagnostic>,
+    #[serde(rename = "dynamicSlotCount")]
+    pub dynamic_slot_count: usize,
+    #[serde(
+        rename = "decisionTree",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub decision_tree: Option<DecisionNode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dispatch: Option<DispatchTable>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bindings: Vec<Binding>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompiledBranch {
+    #[serde(rename = "actionIndex")]
+    pub action_index: usize,
+    pub predicate: CompiledPredicate,
+}
+
+// `path` is the chain of keys (array indices as strings) from the matched value to the capture.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Binding {
+    #[serde(rename = "branchIndex")]
+    pub branch_index: usize,
+    pub name: String,
+    pub path: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum CompiledPredicate {
+    TypeOf {
+        value: String,
+    },
+    IsNull,
+    IsUndefined,
+    Eq {
+        value: Value,
+    },
+    TagEq {
+        key: String,
+        value: Value,
+    },
+    OneOf {
+        values: Vec<Value>,
+    },
+    StartsWith {
+        value: String,
+    },
+    EndsWith {
+        value: String,
+    },
+    Contains {
+        value: String,
+    },
+    Regex {
+        pattern: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        flags: Option<String>,
+    },
+    Range {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<f64>,
+        #[serde(rename = "minInclusive", default)]
+        min_inclusive: bool,
+        #[serde(rename = "maxInclusive", default)]
+        max_inclusive: bool,
+    },
+    Shape {
+        fields: BTreeMap<String, CompiledPredicate>,
+        exact: bool,
+    },
+    Tuple {
+        items: Vec<CompiledPredicate>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rest: Option<Box<CompiledPredicate>>,
+    },
+    ArrayOf {
+        predicate: Box<CompiledPredicate>,
+    },
+    ArrayLength {
+        length: usize,
+    },
+    And {
+        predicates: Vec<CompiledPredicate>,
+    },
+    Or {
+        predicates: Vec<CompiledPredicate>,
+    },
+    Not {
+        predicate: Box<CompiledPredicate>,
+    },
+    Slot {
+        slot: u32,
+    },
+    True,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompileDiagnostic {
+    pub code: String,
+    pub level: Severity,
+    pub message: String,
+    // `None` for program-level findings such as `non_exhaustive`.
+    #[serde(
+        rename = "branchIndex",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub branch_index: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counterexample: Option<Value>,
+    // Set when the counterexample is a missing value (JS `undefined`), which JSON
+    // cannot hold in `counterexample`.
+    #[serde(
+        rename = "counterexampleUndefined",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub counterexample_undefined: bool,
+    // Leads into the JSON form of the branch's `predicate`, e.g. `["fields", "amount"]`;
+    // `Some([])` is the whole predicate and `None` a finding with no location.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<Vec<String>>,
+    #[serde(
+        rename = "shadowedBy",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub shadowed_by: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<RelatedLocation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RelatedLocation {
+    #[serde(rename = "branchIndex")]
+    pub branch_index: usize,
+    #[serde(default)]
+    pub path: Vec<String>,
+    pub message: String,
+}
+
+// `Off` only appears in `CompileOptions.severity`; reported diagnostics are never off.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Off => "off",
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CompileOptions {
+    #[serde(rename = "decisionTree", default)]
+    pub decision_tree: bool,
+    #[serde(rename = "orderByCost", default)]
+    pub order_by_cost: bool,
+    #[serde(default)]
+    pub dispatch: bool,
+    // Overrides the level of every diagnostic with that code.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub severity: BTreeMap<String, Severity>,
+}
+
+// Every diagnostic at `error` level once `CompileOptions.severity` and the branch
+// `allow` lists are applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileError {
+    pub errors: Vec<CompileDiagnostic>,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages = self
+            .errors
+            .iter()
+            .map(|item| format!("{}: {}", item.code, item.message))
+            .collect::<Vec<_>>();
+        write!(formatter, "compilation failed: {}", messages.join("; "))
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+// Reads the JSON form of a program. Like `compile_match_plan`, a top-level array is a
+// bare branch list and anything else must be a `MatchProgram`.
+pub fn parse_program_json(source: &str) -> Result<MatchProgram, MatchPatternError> {
+    let invalid_program = |(path, message)| MatchPatternError::InvalidProgram { path, message };
+    let mut deserializer = serde_json::Deserializer::from_str(source);
+    let program = if source.trim_start().starts_with('[') {
+        deserialize::<Vec<BranchAst>, _>(&mut deserializer).map(|branches| MatchProgram {
+            branches,
+            input_schema: None,
+        })
+    } else {
+        deserialize::<MatchProgram, _>(&mut deserializer)
+    }
+    .map_err(invalid_program)?;
+    deserializer
+        .end()
+        .map_err(|err| invalid_program((Vec::new(), err.to_string())))?;
+    Ok(program)
+}
+
+pub fn compile_program(program: &MatchProgram) -> CompilePlan {
+    compile_program_with_options(program, &CompileOptions::default())
+}
+
+// Like `compile_program_with_options`, but fails when an `error` diagnostic remains.
+pub fn try_compile_program(
+    program: &MatchProgram,
+    options: &CompileOptions,
+) -> Result<CompilePlan, CompileError> {
+    let plan = compile_program_with_options(program, options);
+    let errors = plan
+        .diagnostics
+        .iter()
+        .filter(|item| item.level == Severity::Error)
+        .cloned()
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        Ok(plan)
+    } else {
+        Err(CompileError { errors })
+    }
+}
+
+pub fn compile_program_with_options(
+    program: &MatchProgram,
+    options: &CompileOptions,
+) -> CompilePlan {
+    let mut diagnostics = Vec::new();
+    let mut compiled_branches = Vec::new();
+    let mut previous = Vec::new();
+    let mut dynamic_slot_count = 0usize;
+    let mut bindings: Vec<Binding> = Vec::new();
+
+    for (branch_index, branch) in program.branches.iter().enumerate() {
+        let action_index = branch.action_index.unwrap_or(branch_index);
+        let canonical = canonicalize_predicate(&branch.predicate, false);
+        let lowered = lower_predicate(&canonical);
+        // Slot bookkeeping follows the lowered form, so simplification never hides a slot.
+        let mut predicate = simplify_predicate(&lowered);
+        if options.order_by_cost {
+            predicate = order_by_cost(&predicate);
+        }
+        let analyzed = analyze(&branch.predicate);
+
+        let mut found = Vec::new();
+        collect_bindings(&canonical, Some(&[]), &mut found);
+        let first_binding = bindings.len();
+        for (position, (name, path)) in found.iter().enumerate() {
+            let problem = match path {
+                None => Some("inside an array element pattern, which has no single path"),
+                Some(path) => match bindings[first_binding..]
+                    .iter()
+                    .find(|item| item.name == *name)
+                {
+                    Some(item) if item.path != *path => Some("to more than one path"),
+                    Some(_) => None,
+                    None => {
+                        bindings.push(Binding {
+                            branch_index,
+                            name: name.clone(),
+                            path: path.clone(),
+                        });
+                        None
+                    }
+                },
+            };
+            if let Some(problem) = problem {
+                // Canonicalization keeps binds in order, so the n-th bind of a name is
+                // the n-th one in the source.
+                let occurrence = found[..position]
+                    .iter()
+                    .filter(|(other, _)| other == name)
+                    .count();
+                let source = find_paths(
+                    &branch.predicate,
+                    &|node| matches!(node, PredicateAst::Bind { name: other, .. } if other == name),
+                );
+                diagnostics.push(CompileDiagnostic {
+                    code: "invalid_binding".to_string(),
+                    level: Severity::Warning,
+                    message: format!("branch {} binds `{}` {}", branch_index, name, problem),
+                    branch_index: Some(branch_index),
+                    counterexample: None,
+                    counterexample_undefined: false,
+                    path: source.into_iter().nth(occurrence),
+                    shadowed_by: None,
+                    related: Vec::new(),
+                });
+            }
+        }
+
+        let conflicts = contradictions(&analyzed);
+        if let Some(conflicts) = &conflicts {
+            let reason = if conflicts.is_empty() {
+                String::new()
+            } else {
+                format!(": {}", conflicts.join("; "))
+            };
+            diagnostics.push(CompileDiagnostic {
+                code: "unsatisfiable_branch".to_string(),
+                level: Severity::Warning,
+                message: format!("branch {} can never match{}", branch_index, reason),
+                branch_index: Some(branch_index),
+                counterexample: None,
+                counterexample_undefined: false,
+                path: Some(conflict_path(&branch.predicate)),
+                shadowed_by: None,
+                related: Vec::new(),
+            });
+        }
+
+        // An empty predicate is covered by anything, so shadowing would only repeat the
+        // finding above and blame an unrelated branch.
+        if conflicts.is_none()
+            && let Some(shadowing) = previous.iter().position(|item| covers(item, &analyzed))
+        {
+            diagnostics.push(CompileDiagnostic {
+                code: "unreachable_branch".to_string(),
+                level: Severity::Warning,
+                message: format!(
+                    "branch {} is shadowed by a previous predicate",
+                    branch_index
+                ),
+                branch_index: Some(branch_index),
+                counterexample: None,
+                counterexample_undefined: false,
+                path: Some(Vec::new()),
+                shadowed_by: Some(shadowing),
+                related: vec![RelatedLocation {
+                    branch_index: shadowing,
+                    path: covering_path(&program.branches[shadowing].predicate, &analyzed),
+                    message: format!(
+                        "branch {} already matches every input this branch matches",
+                        shadowing
+                    ),
+                }],
+            });
+        }
+
+        if contains_slot(&lowered) {
+            diagnostics.push(CompileDiagnostic {
+                code: "dynamic_slot".to_string(),
+                level: Severity::Info,
+                message: format!(
+                    "branch {} contains runtime slot predicate and cannot be fully proven at compile time",
+                    branch_index
+                ),
+                branch_index: Some(branch_index),
+                counterexample: None,
+                counterexample_undefined: false,
+                path: find_paths(&branch.predicate, &|node| {
+                    matches!(node, PredicateAst::Slot { .. })
+                })
+                .into_iter()
+                .next(),
+                shadowed_by: None,
+                related: Vec::new(),
+            });
+        }
+
+        for_each_node(&predicate, &mut |node| {
+            if let CompiledPredicate::Regex { pattern, flags } = node
+                && let Err(err) = compile_regex(pattern, flags.as_deref())
+            {
+                diagnostics.push(CompileDiagnostic {
+                    code: "invalid_regex".to_string(),
+                    level: Severity::Warning,
+                    message: format!(
+                        "branch {} has regex /{}/ that the native evaluator cannot compile: {}",
+                        branch_index, pattern, err
+                    ),
+                    branch_index: Some(branch_index),
+                    counterexample: None,
+                    counterexample_undefined: false,
+                    path: find_paths(&branch.predicate, &|node| {
+                        matches!(
+                            node,
+                            PredicateAst::Regex { pattern: source, flags: source_flags }
+                                if source == pattern && source_flags == flags
+                        )
+                    })
+                    .into_iter()
+                    .next(),
+                    shadowed_by: None,
+                    related: Vec::new(),
+                });
+            }
+        });
+
+        dynamic_slot_count = dynamic_slot_count.max(max_slot_index(&lowered).saturating_add(1));
+        previous.push(analyzed);
+        compiled_branches.push(CompiledBranch {
+            action_index,
+            predicate,
+        });
+    }
+
+    if let Some(schema) = &program.input_schema
+        && let Some(counterexample) = find_unhandled_input(schema, &compiled_branches)
+    {
+        let described = counterexample
+            .as_ref()
+            .map(Value::to_string)
+            .unwrap_or_else(|| "undefined".to_string());
+        diagnostics.push(CompileDiagnostic {
+            code: "non_exhaustive".to_string(),
+            level: Severity::Warning,
+            message: format!("input {} is not handled by any branch", described),
+            branch_index: None,
+            counterexample_undefined: counterexample.is_none(),
+            counterexample,
+            path: None,
+            shadowed_by: None,
+            related: Vec::new(),
+        });
+    }
+
+    let decision_tree = options
+        .decision_tree
+        .then(|| build_decision_tree(&compiled_branches));
+    let dispatch = options
+        .dispatch
+        .then(|| build_dispatch_table(&compiled_branches))
+        .flatten();
+
+    // Suppression runs last so every analysis still sees the whole program.
+    diagnostics.retain_mut(|item| {
+        let allowed = item
+            .branch_index
+            .and_then(|index| program.branches.get(index))
+            .is_some_and(|branch| branch.allow.contains(&item.code));
+        if let Some(level) = options.severity.get(&item.code) {
+            item.level = *level;
+        }
+        !allowed && item.level != Severity::Off
+    });
+
+    CompilePlan {
+        version: COMPILE_PLAN_VERSION,
+        branches: compiled_branches,
+        diagnostics,
+        dynamic_slot_count,
+        decision_tree,
+        dispatch,
+        bindings,
+    }
+}
+
+// With `fold_sets`, `or` alternatives on values are folded into `oneOf` (see
+// `fold_value_sets`). Only the analyses see that form: evaluators that predate
+// `oneOf` must still run the emitted plan.
+fn canonicalize_predicate(predicate: &PredicateAst, fold_sets: bool) -> PredicateAst {
+    match predicate {
+        PredicateAst::Shape { fields, exact } => {
+            let next_fields = fields
+                .iter()
+                .map(|(key, value)| (key.clone(), canonicalize_predicate(value, fold_sets)))
+                .collect::<BTreeMap<_, _>>();
+            let maybe_only_field = next_fields.iter().next();
+            let is_single_field = next_fields.len() == 1;
+            let is_array_index = maybe_only_field
+                .map(|(key, _)| key.chars().all(|ch| ch.is_ascii_digit()))
+                .unwrap_or(false);
+
+            if !exact
+                && is_single_field
+                && !is_array_index
+                && matches!(maybe_only_field, Some((_, PredicateAst::Eq { .. })))
+            {
+                let (key, value) = maybe_only_field.unwrap();
+                let PredicateAst::Eq { value } = value else {
+                    unreachable!();
+                };
+
+                PredicateAst::Tag {
+                    key: key.clone(),
+                    value: value.clone(),
+                }
+            } else {
+                PredicateAst::Shape {
+                    fields: next_fields,
+                    exact: *exact,
+                }
+            }
+        }
+        PredicateAst::And { predicates } => PredicateAst::And {
+            predicates: predicates
+                .iter()
+                .map(|predicate| canonicalize_predicate(predicate, fold_sets))
+                .flat_map(|predicate| match predicate {
+                    PredicateAst::And { predicates } => predicates,
+                    other => vec![other],
+                })
+                .collect(),
+        },
+        PredicateAst::Or { predicates } => {
+            let predicates = predicates
+                .iter()
+                .map(|predicate| canonicalize_predicate(predicate, fold_sets))
+                .flat_map(|predicate| match predicate {
+                    PredicateAst::Or { predicates } => predicates,
+                    other => vec![other],
+                })
+                .collect();
+            if fold_sets {
+                fold_value_sets(predicates)
+            } else {
+                PredicateAst::Or { predicates }
+            }
+        }
+        PredicateAst::Not { predicate } => PredicateAst::Not {
+            predicate: Box::new(canonicalize_predicate(predicate, fold_sets)),
+        },
+        PredicateAst::Tuple { items, rest } => PredicateAst::Tuple {
+            items: items
+                .iter()
+                .map(|item| canonicalize_predicate(item, fold_sets))
+                .collect(),
+            rest: rest
+                .as_ref()
+                .map(|node| Box::new(canonicalize_predicate(node, fold_sets))),
+        },
+        PredicateAst::ArrayOf { predicate } => PredicateAst::ArrayOf {
+            predicate: Box::new(canonicalize_predicate(predicate, fold_sets)),
+        },
+        PredicateAst::Bind { name, predicate } => PredicateAst::Bind {
+            name: name.clone(),
+            predicate: Box::new(canonicalize_predicate(predicate, fold_sets)),
+        },
+        _ => predicate.clone(),
+    }
+}
+
+// Folds `Eq` alternatives on the value itself, and `Tag` alternatives on one key,
+// into a single `OneOf` placed where the first of them stood. Value tests never
+// call slots, so pulling them together cannot change which slots run.
+fn fold_value_sets(predicates: Vec<PredicateAst>) -> PredicateAst {
+    let mut groups: Vec<(Option<String>, Vec<Value>, usize)> = Vec::new();
+    for predicate in &predicates {
+        let Some((key, values)) = value_set(predicate) else {
+            continue;
+        };
+        let index = match groups.iter().position(|(item, _, _)| *item == key) {
+            Some(index) => index,
+            None => {
+                groups.push((key, Vec::new(), 0));
+                groups.len() - 1
+            }
+        };
+        let (_, members, count) = &mut groups[index];
+        for value in values {
+            if !members.iter().any(|item| values_equal(item, &value)) {
+                members.push(value);
+            }
+        }
+        *count += 1;
+    }
+
+    if groups.iter().all(|(_, _, count)| *count < 2) {
+        return PredicateAst::Or { predicates };
+    }
+
+    let mut folded = Vec::new();
+    for predicate in predicates {
+        let group = value_set(&predicate)
+            .and_then(|(key, _)| groups.iter_mut().find(|(item, _, _)| *item == key))
+            .filter(|(_, _, count)| *count > 1);
+        let Some((key, members, _)) = group else {
+            folded.push(predicate);
+            continue;
+        };
+        if members.is_empty() {
+            continue;
+        }
+        let set = PredicateAst::OneOf {
+            values: std::mem::take(members),
+        };
+        folded.push(match key {
+            None => set,
+            Some(key) => PredicateAst::Shape {
+                fields: BTreeMap::from([(key.clone(), set)]),
+                exact: false,
+            },
+        });
+    }
+
+    if folded.len() == 1 {
+        folded.remove(0)
+    } else {
+        PredicateAst::Or { predicates: folded }
+    }
+}
+
+// The values a predicate accepts, either for the value itself (`None`) or for `value[key]`.
+fn value_set(predicate: &PredicateAst) -> Option<(Option<String>, Vec<Value>)> {
+    match predicate {
+        PredicateAst::Eq { value } => Some((None, vec![value.clone()])),
+        PredicateAst::OneOf { values } => Some((None, values.clone())),
+        PredicateAst::Tag { key, value } => Some((Some(key.clone()), vec![value.clone()])),
+        PredicateAst::Shape {
+            fields,
+            exact: false,
+        } if fields.len() == 1 => match fields.iter().next() {
+            Some((key, PredicateAst::OneOf { values })) => {
+                Some((Some(key.clone()), values.clone()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn lower_predicate(predicate: &PredicateAst) -> CompiledPredicate {
+    match predicate {
+        PredicateAst::IsNumber => CompiledPredicate::TypeOf {
+            value: "number".to_string(),
+        },
+        PredicateAst::IsString => CompiledPredicate::TypeOf {
+            value: "string".to_string(),
+        },
+        PredicateAst::IsBoolean => CompiledPredicate::TypeOf {
+            value: "boolean".to_string(),
+        },
+        PredicateAst::IsNull => CompiledPredicate::IsNull,
+        PredicateAst::IsUndefined => CompiledPredicate::IsUndefined,
+        PredicateAst::Eq { value } => CompiledPredicate::Eq {
+            value: value.clone(),
+        },
+        PredicateAst::Tag { key, value } => CompiledPredicate::TagEq {
+            key: key.clone(),
+            value: value.clone(),
+        },
+        PredicateAst::OneOf { values } => CompiledPredicate::OneOf {
+            values: values.clone(),
+        },
+        PredicateAst::StartsWith { value } => CompiledPredicate::StartsWith {
+            value: value.clone(),
+        },
+        PredicateAst::EndsWith { value } => CompiledPredicate::EndsWith {
+            value: value.clone(),
+        },
+        PredicateAst::Contains { value } => CompiledPredicate::Contains {
+            value: value.clone(),
+        },
+        PredicateAst::Regex { pattern, flags } => CompiledPredicate::Regex {
+            pattern: pattern.clone(),
+            flags: flags.clone(),
+        },
+        PredicateAst::Range {
+            min,
+            max,
+            min_inclusive,
+            max_inclusive,
+        } => CompiledPredicate::Range {
+            min: *min,
+            max: *max,
+            min_inclusive: *min_inclusive,
+            max_inclusive: *max_inclusive,
+        },
+        PredicateAst::Shape { fields, exact } => CompiledPredicate::Shape {
+            fields: fields
+                .iter()
+                .map(|(key, node)| (key.clone(), lower_predicate(node)))
+                .collect(),
+            exact: *exact,
+        },
+        PredicateAst::And { predicates } => {
+            let flattened = predicates
+                .iter()
+                .map(lower_predicate)
+                .flat_map(|node| match node {
+                    CompiledPredicate::And { predicates } => predicates,
+                    other => vec![other],
+                })
+                .collect();
+            CompiledPredicate::And {
+                predicates: flattened,
+            }
+        }
+        PredicateAst::Or { predicates } => {
+            let flattened = predicates
+                .iter()
+                .map(lower_predicate)
+                .flat_map(|node| match node {
+                    CompiledPredicate::Or { predicates } => predicates,
+                    other => vec![other],
+                })
+                .collect();
+            CompiledPredicate::Or {
+                predicates: flattened,
+            }
+        }
+        PredicateAst::Not { predicate } => CompiledPredicate::Not {
+            predicate: Box::new(lower_predicate(predicate)),
+        },
+        PredicateAst::Tuple { items, rest } => CompiledPredicate::Tuple {
+            items: items.iter().map(lower_predicate).collect(),
+            rest: rest.as_ref().map(|node| Box::new(lower_predicate(node))),
+        },
+        PredicateAst::ArrayOf { predicate } => CompiledPredicate::ArrayOf {
+            predicate: Box::new(lower_predicate(predicate)),
+        },
+        PredicateAst::ArrayLength { length } => CompiledPredicate::ArrayLength { length: *length },
+        PredicateAst::Slot { slot } => CompiledPredicate::Slot { slot: *slot },
+        PredicateAst::Bind { predicate, .. } => lower_predicate(predicate),
+        PredicateAst::Wildcard => CompiledPredicate::True,
+    }
+}
+
+// Records every `bind` with the path it captures. `None` marks a bind under an array
+// element pattern (`arrayOf`, `tuple.rest`), which would capture one value per element.
+fn collect_bindings(
+    predicate: &PredicateAst,
+    path: Option<&[String]>,
+    found: &mut Vec<(String, Option<Vec<String>>)>,
+) {
+    let child = |key: String| {
+        path.map(|path| {
+            let mut next = path.to_vec();
+            next.push(key);
+            next
+        })
+    };
+
+    match predicate {
+        PredicateAst::Bind { name, predicate } => {
+            found.push((name.clone(), path.map(<[String]>::to_vec)));
+            collect_bindings(predicate, path, found);
+        }
+        PredicateAst::Shape { fields, .. } => {
+            for (key, node) in fields {
+                collect_bindings(node, child(key.clone()).as_deref(), found);
+            }
+        }
+        PredicateAst::Tuple { items, rest } => {
+            for (index, node) in items.iter().enumerate() {
+                collect_bindings(node, child(index.to_string()).as_deref(), found);
+            }
+            if let Some(rest) = rest {
+                collect_bindings(rest, None, found);
+            }
+        }
+        PredicateAst::ArrayOf { predicate } => collect_bindings(predicate, None, found),
+        PredicateAst::And { predicates } | PredicateAst::Or { predicates } => predicates
+            .iter()
+            .for_each(|node| collect_bindings(node, path, found)),
+        PredicateAst::Not { predicate } => collect_bindings(predicate, path, found),
+        _ => {}
+    }
+}
+
+// Paths to the nodes `accept` picks, in pre-order, as segments into the predicate's
+// JSON form, e.g. `["predicates", "1", "fields", "amount"]`.
+fn find_paths(
+    predicate: &PredicateAst,
+    accept: &impl Fn(&PredicateAst) -> bool,
+) -> Vec<Vec<String>> {
+    fn walk(
+        predicate: &PredicateAst,
+        path: &mut Vec<String>,
+        accept: &impl Fn(&PredicateAst) -> bool,
+        found: &mut Vec<Vec<String>>,
+    ) {
+        if accept(predicate) {
+            found.push(path.clone());
+        }
+        let mut visit = |segments: &[&str], node: &PredicateAst| {
+            let depth = path.len();
+            path.extend(segments.iter().map(|segment| segment.to_string()));
+            walk(node, path, accept, found);
+            path.truncate(depth);
+        };
+        match predicate {
+            PredicateAst::And { predicates } | PredicateAst::Or { predicates } => {
+                for (index, node) in predicates.iter().enumerate() {
+                    visit(&["predicates", &index.to_string()], node);
+                }
+            }
+            PredicateAst::Not { predicate }
+            | PredicateAst::ArrayOf { predicate }
+            | PredicateAst::Bind { predicate, .. } => visit(&["predicate"], predicate),
+            PredicateAst::Shape { fields, .. } => {
+                for (key, node) in fields {
+                    visit(&["fields", key], node);
+                }
+            }
+            PredicateAst::Tuple { items, rest } => {
+                for (index, node) in items.iter().enumerate() {
+                    visit(&["items", &index.to_string()], node);
+                }
+                if let Some(rest) = rest {
+                    visit(&["rest"], rest);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut found = Vec::new();
+    walk(predicate, &mut Vec::new(), accept, &mut found);
+    found
+}
+
+// The form the shadowing and satisfiability checks reason about.
+fn analyze(predicate: &PredicateAst) -> CompiledPredicate {
+    simplify_predicate(&lower_predicate(&canonicalize_predicate(predicate, true)))
+}
+
+// Path to the smallest node of an unsatisfiable predicate that can never match on its
+// own. Only children every match must satisfy are entered: not `or`, `not`, `arrayOf`
+// or a tuple `rest`, which can hold without their child.
+fn conflict_path(predicate: &PredicateAst) -> Vec<String> {
+    let unsatisfiable = |node: &PredicateAst| contradictions(&analyze(node)).is_some();
+    let children: Vec<(Vec<String>, &PredicateAst)> = match predicate {
+        PredicateAst::And { predicates } => predicates
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (vec!["predicates".to_string(), index.to_string()], node))
+            .collect(),
+        PredicateAst::Bind { predicate, .. } => vec![(vec!["predicate".to_string()], &**predicate)],
+        PredicateAst::Shape { fields, .. } => fields
+            .iter()
+            .map(|(key, node)| (vec!["fields".to_string(), key.clone()], node))
+            .collect(),
+        PredicateAst::Tuple { items, .. } => items
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (vec!["items".to_string(), index.to_string()], node))
+            .collect(),
+        _ => Vec::new(),
+    };
+    match children.into_iter().find(|(_, node)| unsatisfiable(node)) {
+        Some((mut path, node)) => {
+            path.extend(conflict_path(node));
+            path
+        }
+        None => Vec::new(),
+    }
+}
+
+// Path to the alternative of a shadowing `or` that covers `current` by itself, or the
+// whole predicate when no single alternative does.
+fn covering_path(predicate: &PredicateAst, current: &CompiledPredicate) -> Vec<String> {
+    match predicate {
+        PredicateAst::Or { predicates } => predicates
+            .iter()
+            .position(|node| covers(&analyze(node), current))
+            .map(|index| vec!["predicates".to_string(), index.to_string()])
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+pub(crate) fn for_each_node(
+    predicate: &CompiledPredicate,
+    visit: &mut impl FnMut(&CompiledPredicate),
+) {
+    visit(predicate);
+    match predicate {
+        CompiledPredicate::And { predicates } | CompiledPredicate::Or { predicates } => predicates
+            .iter()
+            .for_each(|node| for_each_node(node, visit)),
+        CompiledPredicate::Not { predicate } | CompiledPredicate::ArrayOf { predicate } => {
+            for_each_node(predicate, visit)
+        }
+        CompiledPredicate::Shape { fields, .. } => {
+            fields.values().for_each(|node| for_each_node(node, visit))
+        }
+        CompiledPredicate::Tuple { items, rest } => items
+            .iter()
+            .chain(rest.as_deref())
+            .for_each(|node| for_each_node(node, visit)),
+        _ => {}
+    }
+}
+
+pub(crate) fn contains_slot(predicate: &CompiledPredicate) -> bool {
+    match predicate {
+        CompiledPredicate::Slot { .. } => true,
+        CompiledPredicate::And { predicates } | CompiledPredicate::Or { predicates } => {
+            predicates.iter().any(contains_slot)
+        }
+        CompiledPredicate::Not { predicate } | CompiledPredicate::ArrayOf { predicate } => {
+            contains_slot(predicate)
+        }
+        CompiledPredicate::Shape { fields, .. } => fields.values().any(contains_slot),
+        CompiledPredicate::Tuple { items, rest } => {
+            items.iter().any(contains_slot) || rest.as_deref().is_some_and(contains_slot)
+        }
+        _ => false,
+    }
+}
+
+fn max_slot_index(predicate: &CompiledPredicate) -> usize {
+    match predicate {
+        CompiledPredicate::Slot { slot } => *slot as usize,
+        CompiledPredicate::And { predicates } | CompiledPredicate::Or { predicates } => {
+            predicates.iter().map(max_slot_index).max().unwrap_or(0)
+        }
+        CompiledPredicate::Not { predicate } | CompiledPredicate::ArrayOf { predicate } => {
+            max_slot_index(predicate)
+        }
+        CompiledPredicate::Shape { fields, .. } => {
+            fields.values().map(max_slot_index).max().unwrap_or(0)
+        }
+        CompiledPredicate::Tuple { items, rest } => items
+            .iter()
+            .chain(rest.as_deref())
+            .map(max_slot_index)
+            .max()
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Shared by the other modules' tests.
+    pub(crate) fn branch(predicate: PredicateAst) -> BranchAst {
+        BranchAst {
+            action_index: None,
+            predicate,
+            allow: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_lower_number_tag_shape() {
+        let mut fields = BTreeMap::new();
+        fields.insert(
+            "type".to_string(),
+            PredicateAst::Eq {
+                value: json!("pay"),
+            },
+        );
+        let program = MatchProgram {
+            branches: vec![branch(PredicateAst::And {
+                predicates: vec![
+                    PredicateAst::IsNumber,
+                    PredicateAst::Tag {
+                        key: "kind".to_string(),
+                        value: json!("event"),
+                    },
+                    PredicateAst::Shape {
+                        fields,
+                        exact: false,
+                    },
+                ],
+            })],
+            input_schema: None,
+        };
+
+        let plan = compile_program(&program);
+        assert_eq!(plan.branches.len(), 1);
+        assert!(matches!(
+            &plan.branches[0].predicate,
+            CompiledPredicate::And { predicates } if predicates.len() == 3
+        ));
+    }
+
+    #[test]
+    fn test_shadow_detection_by_typeof() {
+        let program = MatchProgram {
+            branches: vec![
+                branch(PredicateAst::IsNumber),
+                branch(PredicateAst::Eq { value: json!(1) }),
+            ],
+            input_schema: None,
+        };
+
+        let plan = compile_program(&program);
+        assert!(
+            plan.diagnostics
+                .iter()
+                .any(|item| item.code == "unreachable_branch" && item.branch_index == Some(1))
+        );
+    }
+
+    #[test]
+    fn test_shadow_detection_by_tag() {
+        let program = MatchProgram {
+            branches: vec![
+                branch(PredicateAst::Tag {
+                    key: "type".to_string(),
+                    value: json!("pay"),
+                }),
+                branch(PredicateAst::And {
+                    predicates: vec![
+                        PredicateAst::Tag {
+                            key: "type".to_string(),
+                            value: json!("pay"),
+                        },
+                        PredicateAst::Shape {
+                            fields: BTreeMap::from([(
+                                "amount".to_string(),
+                                PredicateAst::IsNumber,
+                            )]),
+                            exact: false,
+                        },
+                    ],
+                }),
+            ],
+            input_schema: None,
+        };
+
+        let plan = compile_program(&program);
+        assert!(
+            plan.diagnostics
+                .iter()
+                .any(|item| item.code == "unreachable_branch" && item.branch_index == Some(1))
+        );
+    }
+
+    #[test]
+    fn test_dynamic_slot_diagnostic() {
+        let program = MatchProgram {
+            branches: vec![branch(PredicateAst::Slot { slot: 2 })],
+            input_schema: None,
+        };
+
+        let plan = compile_program(&program);
+        assert_eq!(plan.dynamic_slot_count, 3);
+        assert!(
+            plan.diagnostics
+                .iter()
+                .any(|item| item.code == "dynamic_slot" && item.branch_index == Some(0))
+        );
+    }
+
+    #[test]
+    fn test_shadow_detection_by_range() {
+        let amount_range = |min: f64| {
+            let mut fields = BTreeMap::new();
+            fields.insert(
+                "amount".to_string(),
+                PredicateAst::Range {
+                    min: Some(min),
+                    max: None,
+                    min_inclusive: false,
+                    max_inclusive: false,
+                },
+            );
+            PredicateAst::Shape {
+                fields,
+                exact: false,
+            }
+        };
+        let unreachable = |first: f64, second: f64| {
+            let program = MatchProgram {
+                branches: vec![branch(amount_range(first)), branch(amount_range(second))],
+                input_schema: None,
+            };
+            compile_program(&program)
+                .diagnostics
+                .iter()
+                .any(|item| item.code == "unreachable_branch" && item.branch_index == Some(1))
+        };
+
+        assert!(unreachable(0.0, 10.0));
+        assert!(!unreachable(10.0, 0.0));
+    }
+
+    #[test]
+    fn test_invalid_regex_diagnostic() {
+        let program = MatchProgram {
+            branches: vec![branch(PredicateAst::Regex {
+                pattern: "(?<=order)\\.paid".to_string(),
+                flags: None,
+            })],
+            input_schema: None,
+        };
+
+        let plan = compile_program(&program);
+        assert!(
+            plan.diagnostics
+                .iter()
+                .any(|item| item.code == "invalid_regex" && item.branch_index == Some(0))
+        );
+    }
+
+    #[test]
+    fn test_or_of_tags_folds_into_one_of() {
+        let status = |values: &[&str]| PredicateAst::Or {
+            predicates: values
+                .iter()
+                .map(|value| PredicateAst::Tag {
+                    key: "status".to_string(),
+                    value: json!(value),
+                })
+                .collect(),
+        };
+        let program = MatchProgram {
+            branches: vec![
+                branch(status(&["paid", "settled", "refunded"])),
+                branch(status(&["settled", "paid"])),
+            ],
+            input_schema: None,
+        };
+
+        assert_eq!(
+            canonicalize_predicate(&program.branches[0].predicate, true),
+            PredicateAst::Shape {
+                fields: BTreeMap::from([(
+                    "status".to_string(),
+                    PredicateAst::OneOf {
+                        values: vec![json!("paid"), json!("settled"), json!("refunded")],
+                    },
+                )]),
+                exact: false,
+            }
+        );
+
+        // The plan keeps the `or`, so evaluators without `oneOf` can still run it.
+        let plan = compile_program(&program);
+        let mut folded = false;
+        for_each_node(&plan.branches[0].predicate, &mut |node| {
+            folded |= matches!(node, CompiledPredicate::OneOf { .. });
+        });
+        assert!(!folded);
+        assert!(
+            plan.diagnostics
+                .iter()
+                .any(|item| item.code == "unreachable_branch" && item.branch_index == Some(1))
+        );
+    }
+
+    #[test]
+    fn test_invalid_binding_diagnostic() {
+        let bind = |name: &str| PredicateAst::Bind {
+            name: name.to_string(),
+            predicate: Box::new(PredicateAst::Wildcard),
+        };
+        let program = MatchProgram {
+            branches: vec![
+                branch(PredicateAst::ArrayOf {
+                    predicate: Box::new(bind("item")),
+                }),
+                branch(PredicateAst::Tuple {
+                    items: vec![bind("id"), bind("id")],
+                    rest: None,
+                }),
+            ],
+            input_schema: None,
+        };
+
+        let plan = compile_program(&program);
+        assert!(
+            plan.diagnostics
+                .iter()
+                .any(|item| item.code == "invalid_binding" && item.branch_index == Some(0))
+        );
+        assert!(
+            plan.diagnostics
+                .iter()
+                .any(|item| item.code == "invalid_binding" && item.branch_index == Some(1))
+        );
+        assert_eq!(plan.bindings.len(), 1);
+        assert_eq!(plan.bindings[0].path, vec!["0".to_string()]);
+    }
+
+    #[test]
+    fn test_unsatisfiable_branch_diagnostic() {
+        let tag = |value: &str| PredicateAst::Tag {
+            key: "type".to_string(),
+            value: json!(value),
+        };
+        let program = MatchProgram {
+            branches: vec![
+                branch(PredicateAst::And {
+                    predicates: vec![PredicateAst::IsNumber, PredicateAst::IsString],
+                }),
+                branch(PredicateAst::And {
+                    predicates: vec![tag("pay"), tag("refund")],
+                }),
+                branch(tag("pay")),
+            ],
+            input_schema: None,
+        };
+
+        let plan = compile_program(&program);
+        let unsatisfiable: Vec<&CompileDiagnostic> = plan
+            .diagnostics
+            .iter()
+            .filter(|item| item.code == "unsatisfiable_branch")
+            .collect();
+        assert_eq!(unsatisfiable.len(), 2);
+        assert_eq!(unsatisfiable[0].branch_index, Some(0));
+        assert_eq!(unsatisfiable[1].branch_index, Some(1));
+        assert!(
+            unsatisfiable[1]
+                .message
+                .contains("value.type == \"pay\" conflicts with value.type == \"refund\"")
+        );
+        assert!(
+            plan.diagnostics
+                .iter()
+                .all(|item| item.code != "unreachable_branch" || item.branch_index == Some(2))
+        );
+    }
+
+    #[test]
+    fn test_unsatisfiable_branch_is_not_reported_as_shadowed() {
+        let program = MatchProgram {
+            branches: vec![
+                branch(PredicateAst::IsNumber),
+                branch(PredicateAst::And {
+                    predicates: vec![PredicateAst::IsString, PredicateAst::IsNumber],
+                }),
+            ],
+            input_schema: None,
+        };
+
+        let plan = compile_program(&program);
+        assert_eq!(plan.diagnostics.len(), 1);
+        assert_eq!(plan.diagnostics[0].code, "unsatisfiable_branch");
+        assert_eq!(plan.diagnostics[0].shadowed_by, None);
+    }
+
+    #[test]
+    fn test_severity_overrides_and_allow() {
+        let mut program = MatchProgram {
+            branches: vec![
+                branch(PredicateAst::IsNumber),
+                branch(PredicateAst::Eq { value: json!(1) }),
+                branch(PredicateAst::Slot { slot: 0 }),
+            ],
+            input_schema: None,
+        };
+        let options = CompileOptions {
+            severity: BTreeMap::from([
+                ("unreachable_branch".to_string(), Severity::Error),
+                ("dynamic_slot".to_string(), Severity::Off),
+            ]),
+            ..CompileOptions::default()
+        };
+
+        let plan = compile_program_with_options(&program, &options);
+        assert_eq!(plan.diagnostics.len(), 1);
+        assert_eq!(plan.diagnostics[0].level, Severity::Error);
+        assert_eq!(
+            serde_json::to_value(&plan.diagnostics[0]).unwrap()["level"],
+            json!("error")
+        );
+        let error = try_compile_program(&program, &options).unwrap_err();
+        assert_eq!(error.errors, plan.diagnostics);
+        assert_eq!(
+            error.to_string(),
+            "compilation failed: unreachable_branch: branch 1 is shadowed by a previous predicate"
+        );
+
+        program.branches[1].allow = vec!["unreachable_branch".to_string()];
+        let plan = try_compile_program(&program, &options).unwrap();
+        assert!(plan.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_diagnostic_locations() {
+        let pay = || PredicateAst::Tag {
+            key: "type".to_string(),
+            value: json!("pay"),
+        };
+        let program = MatchProgram {
+            branches: vec![
+                branch(PredicateAst::Or {
+                    predicates: vec![PredicateAst::IsNull, pay()],
+                }),
+                branch(PredicateAst::Or {
+                    predicates: vec![
+                        PredicateAst::IsNumber,
+                        PredicateAst::Shape {
+                            fields: BTreeMap::from([(
+                                "code".to_string(),
+                                PredicateAst::Regex {
+                                    pattern: "(".to_string(),
+                                    flags: None,
+                                },
+                            )]),
+                            exact: false,
+                        },
+                    ],
+                }),
+                branch(pay()),
+                branch(PredicateAst::ArrayOf {
+                    predicate: Box::new(PredicateAst::Bind {
+                        name: "item".to_string(),
+                        predicate: Box::new(PredicateAst::IsString),
+                    }),
+                }),
+                branch(PredicateAst::Tuple {
+                    items: vec![PredicateAst::IsString, PredicateAst::Slot { slot: 0 }],
+                    rest: None,
+                }),
+                branch(PredicateAst::Shape {
+                    fields: BTreeMap::from([(
+                        "amount".to_string(),
+                        PredicateAst::And {
+                            predicates: vec![PredicateAst::IsString, PredicateAst::IsNumber],
+                        },
+                    )]),
+                    exact: false,
+                }),
+            ],
+            input_schema: None,
+        };
+
+        let plan = compile_program(&program);
+        let find = |code: &str| {
+            plan.diagnostics
+                .iter()
+                .find(|item| item.code == code)
+                .unwrap()
+        };
+        let path = |segments: &[&str]| Some(segments.iter().map(|item| item.to_string()).collect());
+        assert_eq!(
+            find("invalid_regex").path,
+            path(&["predicates", "1", "fields", "code"])
+        );
+        let unreachable = find("unreachable_branch");
+        assert_eq!(unreachable.branch_index, Some(2));
+        assert_eq!(unreachable.path, path(&[]));
+        assert_eq!(unreachable.shadowed_by, Some(0));
+        assert_eq!(unreachable.related[0].branch_index, 0);
+        assert_eq!(unreachable.related[0].path, ["predicates", "1"]);
+        assert_eq!(find("invalid_binding").path, path(&["predicate"]));
+        assert_eq!(find("dynamic_slot").path, path(&["items", "1"]));
+        let unsatisfiable = find("unsatisfiable_branch");
+        assert_eq!(unsatisfiable.branch_index, Some(5));
+        assert_eq!(unsatisfiable.path, path(&["fields", "amount"]));
+
+        let json = serde_json::to_value(unreachable).unwrap();
+        assert_eq!(json["path"], json!([]));
+        assert_eq!(json["shadowedBy"], json!(0));
+        assert_eq!(json["related"][0]["branchIndex"], json!(0));
+        assert_eq!(json["related"][0]["path"], json!(["predicates", "1"]));
+    }
+}
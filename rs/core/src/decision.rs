@@ -0,0 +1,494 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::compiler::{CompiledBranch, CompiledPredicate, contains_slot};
+use crate::evaluator::values_equal;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DecisionNode {
+    Switch {
+        test: DecisionTest,
+        cases: Vec<DecisionCase>,
+        default: Box<DecisionNode>,
+    },
+    Leaf {
+        candidates: Vec<DecisionCandidate>,
+    },
+}
+
+// The value a test switches on:
+// `tag` reads `value[key]` (non-objects and missing keys take `default`),
+// `typeOf` yields the JS `typeof` name of non-null values,
+// `isNull` yields `true` for null.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DecisionTest {
+    Tag { key: String },
+    TypeOf,
+    IsNull,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DecisionCase {
+    pub value: Value,
+    pub node: DecisionNode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DecisionCandidate {
+    #[serde(rename = "branchIndex")]
+    pub branch_index: usize,
+    pub predicate: CompiledPredicate,
+}
+
+// Jump table for programs where every branch starts with `tagEq` on one key or is
+// a wildcard. `cases[i].branches` lists, in order, the branches that can match when
+// `value[key]` equals `cases[i].value`; `fallback` covers every other input.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DispatchTable {
+    pub key: String,
+    pub cases: Vec<DispatchCase>,
+    pub fallback: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DispatchCase {
+    pub value: Value,
+    pub branches: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Candidate {
+    branch_index: usize,
+    tests: Vec<(DecisionTest, Value)>,
+    residual: Vec<CompiledPredicate>,
+}
+
+impl Candidate {
+    fn value_for(&self, test: &DecisionTest) -> Option<&Value> {
+        self.tests
+            .iter()
+            .find(|(item, _)| item == test)
+            .map(|(_, value)| value)
+    }
+
+    fn without(&self, test: &DecisionTest) -> Candidate {
+        Candidate {
+            branch_index: self.branch_index,
+            tests: self
+                .tests
+                .iter()
+                .filter(|(item, _)| item != test)
+                .cloned()
+                .collect(),
+            residual: self.residual.clone(),
+        }
+    }
+
+    fn is_always_true(&self) -> bool {
+        self.tests.is_empty() && self.residual.is_empty()
+    }
+}
+
+pub fn build_decision_tree(branches: &[CompiledBranch]) -> DecisionNode {
+    let candidates = branches
+        .iter()
+        .enumerate()
+        .map(|(branch_index, branch)| split_discriminants(branch_index, &branch.predicate))
+        .collect();
+    build_node(candidates)
+}
+
+pub fn build_dispatch_table(branches: &[CompiledBranch]) -> Option<DispatchTable> {
+    let mut key: Option<&str> = None;
+    let mut tags: Vec<Option<&Value>> = Vec::new();
+    for branch in branches {
+        let first = match &branch.predicate {
+            CompiledPredicate::And { predicates } => predicates.first(),
+            other => Some(other),
+        };
+        match first {
+            Some(CompiledPredicate::TagEq {
+                key: tag_key,
+                value,
+            }) if key.is_none_or(|key| key == tag_key) => {
+                key = Some(tag_key);
+                tags.push(Some(value));
+            }
+            Some(CompiledPredicate::True) => tags.push(None),
+            _ => return None,
+        }
+    }
+
+    let key = key?.to_string();
+    let mut cases: Vec<DispatchCase> = Vec::new();
+    for value in tags.iter().flatten() {
+        if !cases.iter().any(|case| values_equal(&case.value, value)) {
+            cases.push(DispatchCase {
+                value: (*value).clone(),
+                branches: Vec::new(),
+            });
+        }
+    }
+    for case in &mut cases {
+        case.branches = tags
+            .iter()
+            .enumerate()
+            .filter(|(_, tag)| tag.is_none_or(|value| values_equal(value, &case.value)))
+            .map(|(branch_index, _)| branch_index)
+            .collect();
+    }
+    let fallback = tags
+        .iter()
+        .enumerate()
+        .filter(|(_, tag)| tag.is_none())
+        .map(|(branch_index, _)| branch_index)
+        .collect();
+
+    Some(DispatchTable {
+        key,
+        cases,
+        fallback,
+    })
+}
+
+fn split_discriminants(branch_index: usize, predicate: &CompiledPredicate) -> Candidate {
+    let conjuncts = match predicate {
+        CompiledPredicate::And { predicates } => predicates.clone(),
+        CompiledPredicate::True => Vec::new(),
+        other => vec![other.clone()],
+    };
+
+    let mut tests: Vec<(DecisionTest, Value)> = Vec::new();
+    let mut residual = Vec::new();
+    let mut after_slot = false;
+
+    for node in conjuncts {
+        // Hoisting a test across a slot call would change whether the slot runs.
+        after_slot = after_slot || contains_slot(&node);
+        match discriminant(&node) {
+            Some((test, value)) if !after_slot && !tests.iter().any(|(item, _)| *item == test) => {
+                tests.push((test, value));
+            }
+            _ => residual.push(node),
+        }
+    }
+
+    Candidate {
+        branch_index,
+        tests,
+        residual,
+    }
+}
+
+fn discriminant(predicate: &CompiledPredicate) -> Option<(DecisionTest, Value)> {
+    match predicate {
+        CompiledPredicate::TagEq { key, value } => {
+            Some((DecisionTest::Tag { key: key.clone() }, value.clone()))
+        }
+        CompiledPredicate::TypeOf { value } => {
+            Some((DecisionTest::TypeOf, Value::String(value.clone())))
+        }
+        CompiledPredicate::IsNull => Some((DecisionTest::IsNull, Value::Bool(true))),
+        _ => None,
+    }
+}
+
+fn build_node(mut candidates: Vec<Candidate>) -> DecisionNode {
+    if let Some(position) = candidates.iter().position(Candidate::is_always_true) {
+        candidates.truncate(position + 1);
+    }
+
+    let Some(test) = pick_test(&candidates) else {
+        return DecisionNode::Leaf {
+            candidates: candidates.into_iter().map(into_leaf_candidate).collect(),
+        };
+    };
+
+    let mut values: Vec<Value> = Vec::new();
+    for candidate in &candidates {
+        if let Some(value) = candidate.value_for(&test)
+            && !values.iter().any(|item| values_equal(item, value))
+        {
+            values.push(value.clone());
+        }
+    }
+
+    let cases = values
+        .into_iter()
+        .map(|value| {
+            let next = candidates
+                .iter()
+                .filter_map(|candidate| match candidate.value_for(&test) {
+                    None => Some(candidate.clone()),
+                    Some(item) if values_equal(item, &value) => Some(candidate.without(&test)),
+                    Some(_) => None,
+                })
+                .collect();
+            DecisionCase {
+                value,
+                node: build_node(next),
+            }
+        })
+        .collect();
+
+    let default = candidates
+        .into_iter()
+        .filter(|candidate| candidate.value_for(&test).is_none())
+        .collect();
+
+    DecisionNode::Switch {
+        test,
+        cases,
+        default: Box::new(build_node(default)),
+    }
+}
+
+fn pick_test(candidates: &[Candidate]) -> Option<DecisionTest> {
+    let mut counts: Vec<(DecisionTest, usize)> = Vec::new();
+    for candidate in candidates {
+        for (test, _) in &candidate.tests {
+            match counts.iter_mut().find(|(item, _)| item == test) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((test.clone(), 1)),
+            }
+        }
+    }
+
+    // `max_by_key` keeps the last maximum, so reverse to prefer the earliest test on ties.
+    counts
+        .into_iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(test, _)| test)
+}
+
+fn into_leaf_candidate(candidate: Candidate) -> DecisionCandidate {
+    let mut residual = candidate.residual;
+    let predicate = match residual.len() {
+        0 => CompiledPredicate::True,
+        1 => residual.remove(0),
+        _ => CompiledPredicate::And {
+            predicates: residual,
+        },
+    };
+
+    DecisionCandidate {
+        branch_index: candidate.branch_index,
+        predicate,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{
+        BranchAst, CompileOptions, MatchProgram, PredicateAst, compile_program,
+        compile_program_with_options, tests::branch,
+    };
+    use serde_json::json;
+
+    fn tag_branch(value: &str) -> BranchAst {
+        branch(PredicateAst::Tag {
+            key: "type".to_string(),
+            value: json!(value),
+        })
+    }
+
+    fn leaf_branches(node: &DecisionNode) -> Vec<usize> {
+        match node {
+            DecisionNode::Leaf { candidates } => candidates
+                .iter()
+                .map(|candidate| candidate.branch_index)
+                .collect(),
+            DecisionNode::Switch { .. } => panic!("expected leaf, got {:?}", node),
+        }
+    }
+
+    #[test]
+    fn test_tag_router_switches_once() {
+        let program = MatchProgram {
+            branches: vec![
+                tag_branch("pay"),
+                tag_branch("refund"),
+                tag_branch("pay"),
+                branch(PredicateAst::Wildcard),
+            ],
+            input_schema: None,
+        };
+
+        let plan = compile_program_with_options(
+            &program,
+            &CompileOptions {
+                decision_tree: true,
+                ..CompileOptions::default()
+            },
+        );
+        let Some(DecisionNode::Switch {
+            test,
+            cases,
+            default,
+        }) = &plan.decision_tree
+        else {
+            panic!("expected switch root, got {:?}", plan.decision_tree);
+        };
+
+        assert_eq!(
+            *test,
+            DecisionTest::Tag {
+                key: "type".to_string()
+            }
+        );
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].value, json!("pay"));
+        assert_eq!(leaf_branches(&cases[0].node), vec![0]);
+        assert_eq!(cases[1].value, json!("refund"));
+        assert_eq!(leaf_branches(&cases[1].node), vec![1]);
+        assert_eq!(leaf_branches(default), vec![3]);
+    }
+
+    #[test]
+    fn test_equal_numeric_tags_share_a_case() {
+        let tagged = |value: Value| {
+            branch(PredicateAst::Tag {
+                key: "type".to_string(),
+                value,
+            })
+        };
+        let program = MatchProgram {
+            branches: vec![tagged(json!(1)), tagged(json!(1.0))],
+            input_schema: None,
+        };
+
+        let plan = compile_program_with_options(
+            &program,
+            &CompileOptions {
+                decision_tree: true,
+                ..CompileOptions::default()
+            },
+        );
+        let Some(DecisionNode::Switch { cases, .. }) = &plan.decision_tree else {
+            panic!("expected switch root, got {:?}", plan.decision_tree);
+        };
+        assert_eq!(cases.len(), 1);
+        assert_eq!(leaf_branches(&cases[0].node), vec![0]);
+        let input = json!({ "type": 1.0 });
+        assert_eq!(
+            crate::evaluator::evaluate_plan(&plan, Some(&input), |_, _| false),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_residual_keeps_remaining_conjuncts() {
+        let branches = vec![CompiledBranch {
+            action_index: 0,
+            predicate: CompiledPredicate::And {
+                predicates: vec![
+                    CompiledPredicate::TagEq {
+                        key: "type".to_string(),
+                        value: json!("pay"),
+                    },
+                    CompiledPredicate::Eq {
+                        value: json!({ "type": "pay" }),
+                    },
+                ],
+            },
+        }];
+
+        let DecisionNode::Switch { cases, .. } = build_decision_tree(&branches) else {
+            panic!("expected switch root");
+        };
+        let DecisionNode::Leaf { candidates } = &cases[0].node else {
+            panic!("expected leaf");
+        };
+        assert_eq!(
+            candidates[0].predicate,
+            CompiledPredicate::Eq {
+                value: json!({ "type": "pay" }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_tests_after_slot_are_not_hoisted() {
+        let branches = vec![CompiledBranch {
+            action_index: 0,
+            predicate: CompiledPredicate::And {
+                predicates: vec![
+                    CompiledPredicate::Slot { slot: 0 },
+                    CompiledPredicate::TagEq {
+                        key: "type".to_string(),
+                        value: json!("pay"),
+                    },
+                ],
+            },
+        }];
+
+        assert_eq!(leaf_branches(&build_decision_tree(&branches)), vec![0]);
+    }
+
+    #[test]
+    fn test_decision_tree_is_opt_in() {
+        let program = MatchProgram {
+            branches: vec![tag_branch("pay")],
+            input_schema: None,
+        };
+
+        let plan = compile_program(&program);
+        assert!(plan.decision_tree.is_none());
+        assert!(
+            serde_json::to_value(&plan)
+                .unwrap()
+                .get("decisionTree")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_dispatch_table_for_tag_router() {
+        let program = MatchProgram {
+            branches: vec![
+                tag_branch("pay"),
+                tag_branch("refund"),
+                tag_branch("pay"),
+                branch(PredicateAst::Wildcard),
+            ],
+            input_schema: None,
+        };
+        let options = CompileOptions {
+            dispatch: true,
+            ..CompileOptions::default()
+        };
+
+        let dispatch = compile_program_with_options(&program, &options)
+            .dispatch
+            .unwrap();
+        assert_eq!(dispatch.key, "type");
+        assert_eq!(
+            dispatch.cases,
+            vec![
+                DispatchCase {
+                    value: json!("pay"),
+                    branches: vec![0, 2, 3],
+                },
+                DispatchCase {
+                    value: json!("refund"),
+                    branches: vec![1, 3],
+                },
+            ]
+        );
+        assert_eq!(dispatch.fallback, vec![3]);
+
+        let mixed = MatchProgram {
+            branches: vec![tag_branch("pay"), branch(PredicateAst::IsNull)],
+            input_schema: None,
+        };
+        assert!(
+            compile_program_with_options(&mixed, &options)
+                .dispatch
+                .is_none()
+        );
+    }
+}
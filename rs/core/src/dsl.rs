@@ -0,0 +1,1020 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::compiler::{BranchAst, MatchProgram, PredicateAst};
+
+// A parse failure at a 1-based line and column (in characters).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DslError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for DslError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for DslError {}
+
+// Parses a program such as
+//
+//     { type: "pay", amount: > 0 } | { type: "refund" } => 0
+//     _ => 1
+//
+// Each branch is a pattern with an optional `=> actionIndex`; `;` or `,` may
+// separate branches. `#[allow(unreachable_branch)]` before a branch fills its
+// `allow` list. `//` starts a comment.
+pub fn parse_program_text(source: &str) -> Result<MatchProgram, DslError> {
+    let mut parser = Parser::new(tokenize(source)?);
+    let mut branches = Vec::new();
+    loop {
+        while parser.eat(";") || parser.eat(",") {}
+        if parser.peek().kind == TokenKind::End {
+            break;
+        }
+        let allow = parser.allow()?;
+        let predicate = parser.or()?;
+        let action_index = if parser.eat("=>") {
+            Some(parser.integer()?)
+        } else {
+            None
+        };
+        branches.push(BranchAst {
+            action_index,
+            predicate,
+            allow,
+        });
+    }
+    Ok(MatchProgram {
+        branches,
+        input_schema: None,
+    })
+}
+
+pub fn parse_predicate_text(source: &str) -> Result<PredicateAst, DslError> {
+    let mut parser = Parser::new(tokenize(source)?);
+    let predicate = parser.or()?;
+    parser.expect_end()?;
+    Ok(predicate)
+}
+
+// One branch per line. Parsing the output gives back an equal program, except that
+// flags on a missing range bound are dropped.
+pub fn format_program(program: &MatchProgram) -> String {
+    program
+        .branches
+        .iter()
+        .map(|branch| {
+            let allow = if branch.allow.is_empty() {
+                String::new()
+            } else {
+                let codes = branch.allow.iter().map(|code| format_key(code));
+                format!("#[allow({})] ", codes.collect::<Vec<_>>().join(", "))
+            };
+            match branch.action_index {
+                Some(index) => format!(
+                    "{}{} => {}\n",
+                    allow,
+                    format_predicate(&branch.predicate),
+                    index
+                ),
+                // Without `=>`, a following `< 1` would extend a trailing `> 0`.
+                None => format!("{}{};\n", allow, format_predicate(&branch.predicate)),
+            }
+        })
+        .collect()
+}
+
+pub fn format_predicate(predicate: &PredicateAst) -> String {
+    match predicate {
+        PredicateAst::Or { predicates } if predicates.len() > 1 => predicates
+            .iter()
+            .map(|node| match node {
+                PredicateAst::Or { predicates } if predicates.len() > 1 => {
+                    format!("({})", format_predicate(node))
+                }
+                _ => format_predicate(node),
+            })
+            .collect::<Vec<_>>()
+            .join(" | "),
+        PredicateAst::And { predicates } if predicates.len() > 1 => predicates
+            .iter()
+            .map(format_operand)
+            .collect::<Vec<_>>()
+            .join(" & "),
+        PredicateAst::Or { predicates } => format!("or({})", format_list(predicates)),
+        PredicateAst::And { predicates } => format!("and({})", format_list(predicates)),
+        PredicateAst::Not { predicate } => format!("!{}", format_operand(predicate)),
+        PredicateAst::Bind { name, predicate } => {
+            format!("{} @ {}", format_key(name), format_operand(predicate))
+        }
+        PredicateAst::IsNumber => "number".to_string(),
+        PredicateAst::IsString => "string".to_string(),
+        PredicateAst::IsBoolean => "boolean".to_string(),
+        PredicateAst::IsNull => "null".to_string(),
+        PredicateAst::IsUndefined => "undefined".to_string(),
+        PredicateAst::Wildcard => "_".to_string(),
+        PredicateAst::Eq { value } => match value {
+            Value::Bool(_) | Value::Number(_) | Value::String(_) => value.to_string(),
+            _ => format!("eq({})", format_value(value)),
+        },
+        PredicateAst::Tag { key, value } => {
+            format!("tag({}, {})", format_key(key), format_value(value))
+        }
+        PredicateAst::OneOf { values } => format!(
+            "oneOf({})",
+            values
+                .iter()
+                .map(format_value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        PredicateAst::StartsWith { value } => format!("startsWith({})", string_literal(value)),
+        PredicateAst::EndsWith { value } => format!("endsWith({})", string_literal(value)),
+        PredicateAst::Contains { value } => format!("contains({})", string_literal(value)),
+        PredicateAst::Regex { pattern, flags } => match flags {
+            Some(flags) => format!(
+                "regex({}, {})",
+                string_literal(pattern),
+                string_literal(flags)
+            ),
+            None => format!("regex({})", string_literal(pattern)),
+        },
+        PredicateAst::Range {
+            min,
+            max,
+            min_inclusive,
+            max_inclusive,
+        } => {
+            let mut bounds = Vec::new();
+            if let Some(min) = min {
+                let operator = if *min_inclusive { ">=" } else { ">" };
+                bounds.push(format!("{} {}", operator, format_number(*min)));
+            }
+            if let Some(max) = max {
+                let operator = if *max_inclusive { "<=" } else { "<" };
+                bounds.push(format!("{} {}", operator, format_number(*max)));
+            }
+            if bounds.is_empty() {
+                "range()".to_string()
+            } else {
+                bounds.join(" ")
+            }
+        }
+        PredicateAst::Shape { fields, exact } => {
+            let body = if fields.is_empty() {
+                "{}".to_string()
+            } else {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(key, node)| format!("{}: {}", format_key(key), format_predicate(node)))
+                    .collect();
+                format!("{{ {} }}", fields.join(", "))
+            };
+            if *exact {
+                format!("exact {}", body)
+            } else {
+                body
+            }
+        }
+        PredicateAst::Tuple { items, rest } => {
+            let mut parts: Vec<String> = items.iter().map(format_predicate).collect();
+            if let Some(rest) = rest {
+                parts.push(format!("...{}", format_operand(rest)));
+            }
+            format!("[{}]", parts.join(", "))
+        }
+        PredicateAst::ArrayOf { predicate } => format!("arrayOf({})", format_predicate(predicate)),
+        PredicateAst::ArrayLength { length } => format!("arrayLength({})", length),
+        PredicateAst::Slot { slot } => format!("slot({})", slot),
+    }
+}
+
+// Operands of `&`, `!`, `@` and `...` bind tighter than `&` / `|`.
+fn format_operand(predicate: &PredicateAst) -> String {
+    match predicate {
+        PredicateAst::And { predicates } | PredicateAst::Or { predicates }
+            if predicates.len() > 1 =>
+        {
+            format!("({})", format_predicate(predicate))
+        }
+        _ => format_predicate(predicate),
+    }
+}
+
+fn format_list(predicates: &[PredicateAst]) -> String {
+    predicates
+        .iter()
+        .map(format_predicate)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Array(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(format_value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Object(map) if map.is_empty() => "{}".to_string(),
+        Value::Object(map) => {
+            let entries: Vec<String> = map
+                .iter()
+                .map(|(key, item)| format!("{}: {}", format_key(key), format_value(item)))
+                .collect();
+            format!("{{ {} }}", entries.join(", "))
+        }
+        _ => value.to_string(),
+    }
+}
+
+fn format_key(key: &str) -> String {
+    let is_identifier =
+        key.chars().next().is_some_and(is_identifier_start) && key.chars().all(is_identifier_char);
+    if is_identifier {
+        key.to_string()
+    } else {
+        string_literal(key)
+    }
+}
+
+fn string_literal(value: &str) -> String {
+    Value::String(value.to_string()).to_string()
+}
+
+fn format_number(number: f64) -> String {
+    if number.is_infinite() {
+        if number > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        }
+        .to_string()
+    } else {
+        number.to_string()
+    }
+}
+
+fn is_identifier_start(character: char) -> bool {
+    character.is_ascii_alphabetic() || character == '_' || character == '$'
+}
+
+fn is_identifier_char(character: char) -> bool {
+    character.is_ascii_alphanumeric() || character == '_' || character == '$'
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Punct(&'static str),
+    Ident(String),
+    Str(String),
+    Number(String),
+    End,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match &self.kind {
+            TokenKind::Punct(punct) => format!("`{}`", punct),
+            TokenKind::Ident(name) => format!("`{}`", name),
+            TokenKind::Str(_) => "a string".to_string(),
+            TokenKind::Number(text) => format!("`{}`", text),
+            TokenKind::End => "end of input".to_string(),
+        }
+    }
+
+    fn error(&self, message: String) -> DslError {
+        DslError {
+            message,
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+// Longest first, so that `>=` is not read as `>` followed by `=`.
+const PUNCTUATION: [&str; 21] = [
+    "...", "=>", ">=", "<=", ">", "<", "{", "}", "[", "]", "(", ")", ",", ":", ";", "|", "&", "!",
+    "@", "-", "#",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, DslError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let (mut index, mut line, mut column) = (0, 1, 1);
+
+    while index < chars.len() {
+        let start = index;
+        let (token_line, token_column) = (line, column);
+        let error = |message: String| DslError {
+            message,
+            line: token_line,
+            column: token_column,
+        };
+        let current = chars[index];
+        let rest: String = chars[index..chars.len().min(index + 3)].iter().collect();
+
+        let kind = if current == '\n' {
+            index += 1;
+            line += 1;
+            column = 1;
+            continue;
+        } else if current.is_whitespace() {
+            index += 1;
+            column += 1;
+            continue;
+        } else if rest.starts_with("//") {
+            while index < chars.len() && chars[index] != '\n' {
+                index += 1;
+            }
+            continue;
+        } else if current == '"' {
+            index += 1;
+            while index < chars.len() && chars[index] != '"' {
+                if chars[index] == '\n' {
+                    break;
+                }
+                index += if chars[index] == '\\' { 2 } else { 1 };
+            }
+            if index >= chars.len() || chars[index] != '"' {
+                return Err(error("unterminated string".to_string()));
+            }
+            index += 1;
+            let raw: String = chars[start..index].iter().collect();
+            let value = serde_json::from_str::<String>(&raw)
+                .map_err(|err| error(format!("invalid string: {}", err)))?;
+            TokenKind::Str(value)
+        } else if current.is_ascii_digit()
+            || (current == '-' && chars.get(index + 1).is_some_and(char::is_ascii_digit))
+        {
+            index += 1;
+            while index < chars.len()
+                && (chars[index].is_ascii_alphanumeric()
+                    || chars[index] == '.'
+                        && chars.get(index + 1).is_some_and(char::is_ascii_digit)
+                    || matches!(chars[index], '+' | '-') && matches!(chars[index - 1], 'e' | 'E'))
+            {
+                index += 1;
+            }
+            TokenKind::Number(chars[start..index].iter().collect())
+        } else if is_identifier_start(current) {
+            while index < chars.len() && is_identifier_char(chars[index]) {
+                index += 1;
+            }
+            TokenKind::Ident(chars[start..index].iter().collect())
+        } else if let Some(punct) = PUNCTUATION.iter().find(|punct| rest.starts_with(**punct)) {
+            index += punct.chars().count();
+            TokenKind::Punct(punct)
+        } else {
+            return Err(error(format!("unexpected character `{}`", current)));
+        };
+
+        column += index - start;
+        tokens.push(Token {
+            kind,
+            line: token_line,
+            column: token_column,
+        });
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::End,
+        line,
+        column,
+    });
+    Ok(tokens)
+}
+
+// Nesting limit for patterns and values, matching serde_json's recursion limit for the
+// JSON form, so deep input fails cleanly instead of overflowing the stack.
+const MAX_DEPTH: usize = 128;
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Parser {
+            tokens,
+            position: 0,
+            depth: 0,
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    fn peek_second(&self) -> &TokenKind {
+        let index = (self.position + 1).min(self.tokens.len() - 1);
+        &self.tokens[index].kind
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].clone();
+        if token.kind != TokenKind::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if matches!(self.peek().kind, TokenKind::Punct(item) if item == punct) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<(), DslError> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", punct)))
+        }
+    }
+
+    fn expect_end(&self) -> Result<(), DslError> {
+        if self.peek().kind == TokenKind::End {
+            Ok(())
+        } else {
+            Err(self.unexpected("end of input"))
+        }
+    }
+
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, DslError>,
+    ) -> Result<T, DslError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.peek().error("nesting is too deep".to_string()));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn unexpected(&self, expected: &str) -> DslError {
+        let token = self.peek();
+        token.error(format!("expected {}, found {}", expected, token.describe()))
+    }
+
+    // Any number of `#[allow(code, ...)]` attributes.
+    fn allow(&mut self) -> Result<Vec<String>, DslError> {
+        let mut codes = Vec::new();
+        while self.eat("#") {
+            self.expect("[")?;
+            let token = self.next();
+            if !matches!(&token.kind, TokenKind::Ident(name) if name == "allow") {
+                return Err(token.error(format!("expected `allow`, found {}", token.describe())));
+            }
+            codes.extend(self.arguments(Self::key)?);
+            self.expect("]")?;
+        }
+        Ok(codes)
+    }
+
+    fn or(&mut self) -> Result<PredicateAst, DslError> {
+        let mut predicates = vec![self.and()?];
+        while self.eat("|") {
+            predicates.push(self.and()?);
+        }
+        Ok(if predicates.len() == 1 {
+            predicates.remove(0)
+        } else {
+            PredicateAst::Or { predicates }
+        })
+    }
+
+    fn and(&mut self) -> Result<PredicateAst, DslError> {
+        let mut predicates = vec![self.unary()?];
+        while self.eat("&") {
+            predicates.push(self.unary()?);
+        }
+        Ok(if predicates.len() == 1 {
+            predicates.remove(0)
+        } else {
+            PredicateAst::And { predicates }
+        })
+    }
+
+    // Every nested pattern passes through here.
+    fn unary(&mut self) -> Result<PredicateAst, DslError> {
+        self.nested(Self::prefixed)
+    }
+
+    fn prefixed(&mut self) -> Result<PredicateAst, DslError> {
+        if self.eat("!") {
+            return Ok(PredicateAst::Not {
+                predicate: Box::new(self.unary()?),
+            });
+        }
+        if *self.peek_second() == TokenKind::Punct("@") {
+            let name = self.key()?;
+            self.expect("@")?;
+            return Ok(PredicateAst::Bind {
+                name,
+                predicate: Box::new(self.unary()?),
+            });
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<PredicateAst, DslError> {
+        let token = self.peek().clone();
+        match &token.kind {
+            TokenKind::Punct("(") => {
+                self.next();
+                let predicate = self.or()?;
+                self.expect(")")?;
+                Ok(predicate)
+            }
+            TokenKind::Punct("{") => Ok(PredicateAst::Shape {
+                fields: self.fields()?,
+                exact: false,
+            }),
+            TokenKind::Punct("[") => self.tuple(),
+            TokenKind::Punct(">" | ">=" | "<" | "<=") => self.range(),
+            TokenKind::Str(_) | TokenKind::Number(_) => Ok(PredicateAst::Eq {
+                value: self.value()?,
+            }),
+            TokenKind::Ident(name) => {
+                self.next();
+                self.keyword(&token, name)
+            }
+            _ => Err(self.unexpected("a pattern")),
+        }
+    }
+
+    fn keyword(&mut self, token: &Token, name: &str) -> Result<PredicateAst, DslError> {
+        let predicate = match name {
+            "_" => PredicateAst::Wildcard,
+            "number" => PredicateAst::IsNumber,
+            "string" => PredicateAst::IsString,
+            "boolean" => PredicateAst::IsBoolean,
+            "null" => PredicateAst::IsNull,
+            "undefined" => PredicateAst::IsUndefined,
+            "true" | "false" => PredicateAst::Eq {
+                value: Value::Bool(name == "true"),
+            },
+            "exact" => PredicateAst::Shape {
+                fields: self.fields()?,
+                exact: true,
+            },
+            "eq" => PredicateAst::Eq {
+                value: self.single(Self::value)?,
+            },
+            "tag" => {
+                self.expect("(")?;
+                let key = self.key()?;
+                self.expect(",")?;
+                let value = self.value()?;
+                self.eat(",");
+                self.expect(")")?;
+                PredicateAst::Tag { key, value }
+            }
+            "oneOf" => PredicateAst::OneOf {
+                values: self.arguments(Self::value)?,
+            },
+            "startsWith" => PredicateAst::StartsWith {
+                value: self.single(Self::string)?,
+            },
+            "endsWith" => PredicateAst::EndsWith {
+                value: self.single(Self::string)?,
+            },
+            "contains" => PredicateAst::Contains {
+                value: self.single(Self::string)?,
+            },
+            "regex" => {
+                let mut strings = self.arguments(Self::string)?.into_iter();
+                match (strings.next(), strings.next(), strings.next()) {
+                    (Some(pattern), flags, None) => PredicateAst::Regex { pattern, flags },
+                    _ => {
+                        return Err(token.error("expected `regex(pattern, flags?)`".to_string()));
+                    }
+                }
+            }
+            "range" => {
+                self.arguments(|parser| Err::<(), _>(parser.unexpected("`)`")))?;
+                PredicateAst::Range {
+                    min: None,
+                    max: None,
+                    min_inclusive: false,
+                    max_inclusive: false,
+                }
+            }
+            "arrayOf" => PredicateAst::ArrayOf {
+                predicate: Box::new(self.single(Self::or)?),
+            },
+            "arrayLength" => PredicateAst::ArrayLength {
+                length: self.single(Self::integer)?,
+            },
+            "slot" => PredicateAst::Slot {
+                slot: self.single(Self::integer)?,
+            },
+            "and" => PredicateAst::And {
+                predicates: self.arguments(Self::or)?,
+            },
+            "or" => PredicateAst::Or {
+                predicates: self.arguments(Self::or)?,
+            },
+            _ => return Err(token.error(format!("unknown pattern `{}`", name))),
+        };
+        Ok(predicate)
+    }
+
+    // `> 0`, `<= 10` or both bounds, lower first or last: `>= 0 < 10`.
+    fn range(&mut self) -> Result<PredicateAst, DslError> {
+        let (mut min, mut max) = (None, None);
+        let (mut min_inclusive, mut max_inclusive) = (false, false);
+        while let TokenKind::Punct(operator @ (">" | ">=" | "<" | "<=")) = self.peek().kind {
+            let lower = operator.starts_with('>');
+            if (lower && min.is_some()) || (!lower && max.is_some()) {
+                break;
+            }
+            self.next();
+            let bound = self.number()?;
+            if lower {
+                min = Some(bound);
+                min_inclusive = operator == ">=";
+            } else {
+                max = Some(bound);
+                max_inclusive = operator == "<=";
+            }
+        }
+        Ok(PredicateAst::Range {
+            min,
+            max,
+            min_inclusive,
+            max_inclusive,
+        })
+    }
+
+    // `{ key: pattern, ... }`
+    fn fields(&mut self) -> Result<BTreeMap<String, PredicateAst>, DslError> {
+        self.expect("{")?;
+        let mut fields = BTreeMap::new();
+        while !self.eat("}") {
+            let token = self.peek().clone();
+            let key = self.key()?;
+            self.expect(":")?;
+            if fields.insert(key.clone(), self.or()?).is_some() {
+                return Err(token.error(format!("duplicate field `{}`", key)));
+            }
+            if !self.eat(",") {
+                self.expect("}")?;
+                break;
+            }
+        }
+        Ok(fields)
+    }
+
+    // `[pattern, ..., ...rest]`
+    fn tuple(&mut self) -> Result<PredicateAst, DslError> {
+        self.expect("[")?;
+        let mut items = Vec::new();
+        let mut rest = None;
+        while !self.eat("]") {
+            if self.eat("...") {
+                rest = Some(Box::new(self.unary()?));
+                self.eat(",");
+                self.expect("]")?;
+                break;
+            }
+            items.push(self.or()?);
+            if !self.eat(",") {
+                self.expect("]")?;
+                break;
+            }
+        }
+        Ok(PredicateAst::Tuple { items, rest })
+    }
+
+    // A JSON value, with `[...]` arrays and `{ key: value }` objects.
+    fn value(&mut self) -> Result<Value, DslError> {
+        self.nested(Self::literal)
+    }
+
+    fn literal(&mut self) -> Result<Value, DslError> {
+        let token = self.next();
+        match &token.kind {
+            TokenKind::Str(value) => Ok(Value::String(value.clone())),
+            TokenKind::Number(text) => serde_json::from_str::<Value>(text)
+                .ok()
+                .filter(Value::is_number)
+                .ok_or_else(|| token.error(format!("invalid number `{}`", text))),
+            TokenKind::Ident(name) if name == "true" || name == "false" => {
+                Ok(Value::Bool(name == "true"))
+            }
+            TokenKind::Ident(name) if name == "null" => Ok(Value::Null),
+            TokenKind::Punct("[") => {
+                let mut items = Vec::new();
+                while !self.eat("]") {
+                    items.push(self.value()?);
+                    if !self.eat(",") {
+                        self.expect("]")?;
+                        break;
+                    }
+                }
+                Ok(Value::Array(items))
+            }
+            TokenKind::Punct("{") => {
+                let mut map = serde_json::Map::new();
+                while !self.eat("}") {
+                    let key = self.key()?;
+                    self.expect(":")?;
+                    map.insert(key, self.value()?);
+                    if !self.eat(",") {
+                        self.expect("}")?;
+                        break;
+                    }
+                }
+                Ok(Value::Object(map))
+            }
+            _ => Err(token.error(format!("expected a value, found {}", token.describe()))),
+        }
+    }
+
+    // Range bounds may also be `Infinity` or `-Infinity`.
+    fn number(&mut self) -> Result<f64, DslError> {
+        let negative = self.eat("-");
+        let token = self.next();
+        let number = match &token.kind {
+            TokenKind::Number(text) if !negative => {
+                text.parse::<f64>().ok().filter(|number| number.is_finite())
+            }
+            TokenKind::Ident(name) if name == "Infinity" => Some(f64::INFINITY),
+            _ => None,
+        };
+        number
+            .map(|number| if negative { -number } else { number })
+            .ok_or_else(|| token.error(format!("expected a number, found {}", token.describe())))
+    }
+
+    fn integer<T: std::str::FromStr>(&mut self) -> Result<T, DslError> {
+        let token = self.next();
+        match &token.kind {
+            TokenKind::Number(text) => text.parse::<T>().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            token.error(format!(
+                "expected a non-negative integer, found {}",
+                token.describe()
+            ))
+        })
+    }
+
+    fn string(&mut self) -> Result<String, DslError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Str(value) => Ok(value),
+            _ => Err(token.error(format!("expected a string, found {}", token.describe()))),
+        }
+    }
+
+    // Field names and binding names: identifiers, keywords included, or strings.
+    fn key(&mut self) -> Result<String, DslError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Ident(name) => Ok(name),
+            TokenKind::Str(value) => Ok(value),
+            _ => Err(token.error(format!("expected a key, found {}", token.describe()))),
+        }
+    }
+
+    // A parenthesized, comma-separated argument list.
+    fn arguments<T>(
+        &mut self,
+        parse: impl Fn(&mut Self) -> Result<T, DslError>,
+    ) -> Result<Vec<T>, DslError> {
+        self.expect("(")?;
+        let mut items = Vec::new();
+        while !self.eat(")") {
+            items.push(parse(self)?);
+            if !self.eat(",") {
+                self.expect(")")?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn single<T>(
+        &mut self,
+        parse: impl Fn(&mut Self) -> Result<T, DslError>,
+    ) -> Result<T, DslError> {
+        self.expect("(")?;
+        let item = parse(self)?;
+        self.eat(",");
+        self.expect(")")?;
+        Ok(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::tests::branch;
+    use serde_json::json;
+
+    fn range(min: Option<f64>, max: Option<f64>, inclusive: bool) -> PredicateAst {
+        PredicateAst::Range {
+            min,
+            max,
+            min_inclusive: inclusive && min.is_some(),
+            max_inclusive: inclusive && max.is_some(),
+        }
+    }
+
+    #[test]
+    fn test_parses_program() {
+        let program = parse_program_text(
+            r#"
+            // payments first
+            { type: "pay", amount: > 0 } | { type: "refund" } => 0
+            [string, ...number] => 1;
+            total @ >= 0 <= 100 & !eq(null)
+            "#,
+        )
+        .unwrap();
+
+        let shape = |fields: Vec<(&str, PredicateAst)>| PredicateAst::Shape {
+            fields: fields
+                .into_iter()
+                .map(|(key, node)| (key.to_string(), node))
+                .collect(),
+            exact: false,
+        };
+        let eq = |value: Value| PredicateAst::Eq { value };
+        assert_eq!(
+            program.branches,
+            vec![
+                BranchAst {
+                    action_index: Some(0),
+                    predicate: PredicateAst::Or {
+                        predicates: vec![
+                            shape(vec![
+                                ("type", eq(json!("pay"))),
+                                ("amount", range(Some(0.0), None, false)),
+                            ]),
+                            shape(vec![("type", eq(json!("refund")))]),
+                        ],
+                    },
+                    allow: Vec::new(),
+                },
+                BranchAst {
+                    action_index: Some(1),
+                    predicate: PredicateAst::Tuple {
+                        items: vec![PredicateAst::IsString],
+                        rest: Some(Box::new(PredicateAst::IsNumber)),
+                    },
+                    allow: Vec::new(),
+                },
+                branch(PredicateAst::And {
+                    predicates: vec![
+                        PredicateAst::Bind {
+                            name: "total".to_string(),
+                            predicate: Box::new(range(Some(0.0), Some(100.0), true)),
+                        },
+                        PredicateAst::Not {
+                            predicate: Box::new(eq(Value::Null)),
+                        },
+                    ],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_round_trips() {
+        let predicates = vec![
+            PredicateAst::Or {
+                predicates: vec![
+                    PredicateAst::And {
+                        predicates: vec![PredicateAst::IsNumber, range(None, Some(-1.5), false)],
+                    },
+                    PredicateAst::Or {
+                        predicates: vec![PredicateAst::IsNull, PredicateAst::IsUndefined],
+                    },
+                ],
+            },
+            PredicateAst::Shape {
+                fields: BTreeMap::from([
+                    (
+                        "first name".to_string(),
+                        PredicateAst::Regex {
+                            pattern: "^a\"b\\d".to_string(),
+                            flags: Some("i".to_string()),
+                        },
+                    ),
+                    (
+                        "kind".to_string(),
+                        PredicateAst::Tag {
+                            key: "type".to_string(),
+                            value: json!({ "nested key": [1, 2.5, null, true] }),
+                        },
+                    ),
+                ]),
+                exact: true,
+            },
+            PredicateAst::Tuple {
+                items: vec![
+                    PredicateAst::OneOf {
+                        values: vec![json!("a"), json!(1e21)],
+                    },
+                    PredicateAst::Slot { slot: 3 },
+                ],
+                rest: Some(Box::new(PredicateAst::Or {
+                    predicates: vec![PredicateAst::Wildcard, PredicateAst::IsBoolean],
+                })),
+            },
+            PredicateAst::Not {
+                predicate: Box::new(PredicateAst::And {
+                    predicates: vec![
+                        PredicateAst::ArrayOf {
+                            predicate: Box::new(PredicateAst::StartsWith {
+                                value: "SKU-".to_string(),
+                            }),
+                        },
+                        PredicateAst::ArrayLength { length: 2 },
+                    ],
+                }),
+            },
+            PredicateAst::And {
+                predicates: vec![range(Some(f64::NEG_INFINITY), None, true)],
+            },
+            PredicateAst::Or { predicates: vec![] },
+            range(None, None, false),
+        ];
+
+        for predicate in predicates {
+            let text = format_predicate(&predicate);
+            assert_eq!(parse_predicate_text(&text), Ok(predicate), "{}", text);
+        }
+
+        let program = parse_program_text("> 0; < 1 => 2").unwrap();
+        assert_eq!(format_program(&program), "> 0;\n< 1 => 2\n");
+        assert_eq!(parse_program_text(&format_program(&program)), Ok(program));
+
+        let program =
+            parse_program_text("_ => 0\n#[allow(unreachable_branch)] #[allow(\"x y\")] 1 => 1")
+                .unwrap();
+        assert_eq!(program.branches[1].allow, ["unreachable_branch", "x y"]);
+        assert_eq!(
+            format_program(&program),
+            "_ => 0\n#[allow(unreachable_branch, \"x y\")] 1 => 1\n"
+        );
+        assert_eq!(parse_program_text(&format_program(&program)), Ok(program));
+    }
+
+    #[test]
+    fn test_errors_have_positions() {
+        let error = parse_program_text("{ type: \"pay\" } => 0\n{ amount: >> 0 }").unwrap_err();
+        assert_eq!(error.to_string(), "2:12: expected a number, found `>`");
+
+        let error = parse_predicate_text("{ a: 1, a: 2 }").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 9));
+        assert_eq!(error.message, "duplicate field `a`");
+
+        let error = parse_predicate_text("matches(\"x\")").unwrap_err();
+        assert_eq!(error.to_string(), "1:1: unknown pattern `matches`");
+
+        let error = parse_predicate_text("  \"open").unwrap_err();
+        assert_eq!(error.to_string(), "1:3: unterminated string");
+    }
+
+    #[test]
+    fn test_rejects_deep_nesting() {
+        let error = parse_predicate_text(&("!".repeat(10000) + "_")).unwrap_err();
+        assert_eq!(error.to_string(), "1:129: nesting is too deep");
+
+        let arrays = format!("eq({}{})", "[".repeat(10000), "]".repeat(10000));
+        let error = parse_predicate_text(&arrays).unwrap_err();
+        assert_eq!(error.message, "nesting is too deep");
+
+        let shapes = "{ a: ".repeat(MAX_DEPTH - 1) + "_" + &" }".repeat(MAX_DEPTH - 1);
+        assert!(parse_predicate_text(&shapes).is_ok());
+    }
+}
@@ -0,0 +1,295 @@
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use js_sys::{Array, Object, Reflect};
+use serde::Deserializer;
+#[cfg(feature = "wasm")]
+use serde::Serialize;
+use serde::de::{Deserialize, DeserializeOwned};
+use serde_json::Value;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::binary::DecodeError;
+use crate::compiler::CompileError;
+use crate::migration::PlanLoadError;
+
+// Every wasm export fails with one of these. `path` leads from the export's input
+// to the offending node, in the same segment form as `PredicateFailure.path`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchPatternError {
+    PatternsNotArray {
+        path: Vec<String>,
+    },
+    PatternNotObject {
+        path: Vec<String>,
+    },
+    MissingField {
+        path: Vec<String>,
+        field: String,
+    },
+    InvalidField {
+        path: Vec<String>,
+        field: String,
+        expected: String,
+    },
+    UnknownPatternType {
+        path: Vec<String>,
+        found: String,
+    },
+    // A function pattern (or a proxy trap) threw while matching.
+    PatternThrew {
+        path: Vec<String>,
+        message: String,
+    },
+    InvalidProgram {
+        path: Vec<String>,
+        message: String,
+    },
+    InvalidOptions {
+        path: Vec<String>,
+        message: String,
+    },
+    // `path` points at the branch of the first error.
+    CompileFailed {
+        path: Vec<String>,
+        error: CompileError,
+    },
+    InvalidValue {
+        path: Vec<String>,
+        message: String,
+    },
+    PlanLoad(PlanLoadError),
+    Decode(DecodeError),
+    Serialize {
+        message: String,
+    },
+}
+
+impl MatchPatternError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            MatchPatternError::PatternsNotArray { .. } => "patterns_not_array",
+            MatchPatternError::PatternNotObject { .. } => "pattern_not_object",
+            MatchPatternError::MissingField { .. } => "missing_field",
+            MatchPatternError::InvalidField { .. } => "invalid_field",
+            MatchPatternError::UnknownPatternType { .. } => "unknown_pattern_type",
+            MatchPatternError::PatternThrew { .. } => "pattern_threw",
+            MatchPatternError::InvalidProgram { .. } => "invalid_program",
+            MatchPatternError::InvalidOptions { .. } => "invalid_options",
+            MatchPatternError::CompileFailed { .. } => "compile_failed",
+            MatchPatternError::InvalidValue { .. } => "invalid_value",
+            MatchPatternError::PlanLoad(err) => match err {
+                PlanLoadError::MissingVersion => "missing_plan_version",
+                PlanLoadError::UnsupportedVersion { .. } => "unsupported_plan_version",
+                PlanLoadError::NewerVersion { .. } => "newer_plan_version",
+                PlanLoadError::MigrationFailed { .. } => "plan_migration_failed",
+                PlanLoadError::Invalid { .. } => "invalid_plan",
+            },
+            MatchPatternError::Decode(_) => "invalid_encoded_plan",
+            MatchPatternError::Serialize { .. } => "serialize_failed",
+        }
+    }
+
+    pub fn path(&self) -> Vec<String> {
+        match self {
+            MatchPatternError::PatternsNotArray { path }
+            | MatchPatternError::PatternNotObject { path }
+            | MatchPatternError::MissingField { path, .. }
+            | MatchPatternError::InvalidField { path, .. }
+            | MatchPatternError::UnknownPatternType { path, .. }
+            | MatchPatternError::PatternThrew { path, .. }
+            | MatchPatternError::InvalidProgram { path, .. }
+            | MatchPatternError::InvalidOptions { path, .. }
+            | MatchPatternError::CompileFailed { path, .. }
+            | MatchPatternError::InvalidValue { path, .. }
+            | MatchPatternError::PlanLoad(PlanLoadError::Invalid { path, .. }) => path.clone(),
+            MatchPatternError::PlanLoad(
+                PlanLoadError::MissingVersion
+                | PlanLoadError::UnsupportedVersion { .. }
+                | PlanLoadError::NewerVersion { .. },
+            ) => vec!["version".to_string()],
+            MatchPatternError::PlanLoad(PlanLoadError::MigrationFailed { .. })
+            | MatchPatternError::Decode(_)
+            | MatchPatternError::Serialize { .. } => Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    pub fn thrown(path: Vec<String>, exception: &JsValue) -> Self {
+        let message = exception
+            .dyn_ref::<js_sys::Error>()
+            .map(|error| String::from(error.message()))
+            .or_else(|| exception.as_string())
+            .unwrap_or_else(|| format!("{:?}", exception));
+        MatchPatternError::PatternThrew { path, message }
+    }
+
+    // Puts `segment` in front of the path, for errors raised below the caller.
+    pub fn within(mut self, segment: String) -> Self {
+        match &mut self {
+            MatchPatternError::PatternsNotArray { path }
+            | MatchPatternError::PatternNotObject { path }
+            | MatchPatternError::MissingField { path, .. }
+            | MatchPatternError::InvalidField { path, .. }
+            | MatchPatternError::UnknownPatternType { path, .. }
+            | MatchPatternError::PatternThrew { path, .. }
+            | MatchPatternError::InvalidProgram { path, .. }
+            | MatchPatternError::InvalidOptions { path, .. }
+            | MatchPatternError::CompileFailed { path, .. }
+            | MatchPatternError::InvalidValue { path, .. }
+            | MatchPatternError::PlanLoad(PlanLoadError::Invalid { path, .. }) => {
+                path.insert(0, segment)
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+impl fmt::Display for MatchPatternError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchPatternError::PatternsNotArray { .. } => {
+                write!(formatter, "patterns must be an array")
+            }
+            MatchPatternError::PatternNotObject { .. } => {
+                write!(formatter, "pattern must be an object")
+            }
+            MatchPatternError::MissingField { field, .. } => {
+                write!(formatter, "missing '{}' field", field)
+            }
+            MatchPatternError::InvalidField {
+                field, expected, ..
+            } => write!(formatter, "'{}' must be {}", field, expected),
+            MatchPatternError::UnknownPatternType { found, .. } => {
+                write!(formatter, "unknown pattern type: {}", found)
+            }
+            MatchPatternError::PatternThrew { message, .. } => {
+                write!(formatter, "pattern threw: {}", message)
+            }
+            MatchPatternError::InvalidProgram { message, .. } => {
+                write!(formatter, "invalid compile program: {}", message)
+            }
+            MatchPatternError::InvalidOptions { message, .. } => {
+                write!(formatter, "invalid compile options: {}", message)
+            }
+            MatchPatternError::CompileFailed { error, .. } => write!(formatter, "{}", error),
+            MatchPatternError::InvalidValue { message, .. } => {
+                write!(formatter, "invalid match value: {}", message)
+            }
+            MatchPatternError::PlanLoad(err) => write!(formatter, "invalid compile plan: {}", err),
+            MatchPatternError::Decode(err) => write!(formatter, "invalid encoded plan: {}", err),
+            MatchPatternError::Serialize { message } => {
+                write!(formatter, "failed to serialize result: {}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MatchPatternError {}
+
+impl From<PlanLoadError> for MatchPatternError {
+    fn from(err: PlanLoadError) -> Self {
+        MatchPatternError::PlanLoad(err)
+    }
+}
+
+impl From<DecodeError> for MatchPatternError {
+    fn from(err: DecodeError) -> Self {
+        MatchPatternError::Decode(err)
+    }
+}
+
+// A JS `Error` with `code` and `path` (an array of strings) set. `compile_failed`
+// also carries the error-level `diagnostics`.
+#[cfg(feature = "wasm")]
+impl From<MatchPatternError> for JsValue {
+    fn from(err: MatchPatternError) -> Self {
+        let path = err
+            .path()
+            .iter()
+            .map(|segment| JsValue::from_str(segment))
+            .collect::<Array>();
+        let error = Object::from(js_sys::Error::new(&err.to_string()));
+        let set = |key: &str, value: &JsValue| {
+            let _ = Reflect::set(&error, &JsValue::from_str(key), value);
+        };
+        set("code", &JsValue::from_str(err.code()));
+        set("path", &path);
+        if let MatchPatternError::CompileFailed { error: failure, .. } = &err {
+            let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+            let diagnostics = failure
+                .errors
+                .serialize(&serializer)
+                .unwrap_or(JsValue::UNDEFINED);
+            set("diagnostics", &diagnostics);
+        }
+        error.into()
+    }
+}
+
+// Like `serde_json::from_value`, but also reports where deserialization failed.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, (Vec<String>, String)> {
+    deserialize(value)
+}
+
+pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, (Vec<String>, String)>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let path = err
+            .path()
+            .iter()
+            .filter_map(|segment| match segment {
+                serde_path_to_error::Segment::Seq { index } => Some(index.to_string()),
+                serde_path_to_error::Segment::Map { key } => Some(key.clone()),
+                _ => None,
+            })
+            .collect();
+        (path, err.into_inner().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::MatchProgram;
+    use crate::migration::load_plan;
+    use serde_json::json;
+
+    #[test]
+    fn test_codes_and_paths() {
+        let program = json!({
+            "branches": [
+                { "actionIndex": 0, "predicate": { "kind": "isString" } },
+                { "actionIndex": "1", "predicate": { "kind": "isNumber" } }
+            ]
+        });
+        let (path, message) = from_value::<MatchProgram>(program).unwrap_err();
+        let err = MatchPatternError::InvalidProgram { path, message };
+        assert_eq!(err.code(), "invalid_program");
+        assert_eq!(err.path(), ["branches", "1", "actionIndex"]);
+
+        let err = MatchPatternError::from(load_plan(json!({ "version": 9 })).unwrap_err());
+        assert_eq!(err.code(), "newer_plan_version");
+        assert_eq!(err.path(), ["version"]);
+        assert_eq!(
+            err.to_string(),
+            "invalid compile plan: plan version 9 is newer than the supported version 2"
+        );
+
+        let err = MatchPatternError::from(load_plan(json!({ "version": 2 })).unwrap_err());
+        assert_eq!(err.code(), "invalid_plan");
+
+        let err = MatchPatternError::PatternThrew {
+            path: vec!["x".to_string()],
+            message: "boom".to_string(),
+        }
+        .within("inner".to_string())
+        .within("2".to_string());
+        assert_eq!(err.path(), ["2", "inner", "x"]);
+    }
+}
@@ -0,0 +1,634 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::compiler::{CompilePlan, CompiledPredicate};
+use crate::decision::{DecisionNode, DecisionTest};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanMatch {
+    pub action_index: usize,
+    // Captures whose path is missing from the input are left out, like JS `undefined`.
+    pub bindings: BTreeMap<String, Value>,
+}
+
+// `None` stands in for JS `undefined`, e.g. a missing shape field.
+pub fn evaluate_plan<S>(plan: &CompilePlan, value: Option<&Value>, slots: S) -> Option<usize>
+where
+    S: FnMut(u32, Option<&Value>) -> bool,
+{
+    matching_branch(plan, value, slots).map(|branch_index| plan.branches[branch_index].action_index)
+}
+
+pub fn evaluate_plan_with_bindings<S>(
+    plan: &CompilePlan,
+    value: Option<&Value>,
+    slots: S,
+) -> Option<PlanMatch>
+where
+    S: FnMut(u32, Option<&Value>) -> bool,
+{
+    let branch_index = matching_branch(plan, value, slots)?;
+    let bindings = plan
+        .bindings
+        .iter()
+        .filter(|binding| binding.branch_index == branch_index)
+        .filter_map(|binding| {
+            let captured = binding
+                .path
+                .iter()
+                .try_fold(value?, |current, key| field(current, key))?;
+            Some((binding.name.clone(), captured.clone()))
+        })
+        .collect();
+
+    Some(PlanMatch {
+        action_index: plan.branches[branch_index].action_index,
+        bindings,
+    })
+}
+
+fn matching_branch<S>(plan: &CompilePlan, value: Option<&Value>, mut slots: S) -> Option<usize>
+where
+    S: FnMut(u32, Option<&Value>) -> bool,
+{
+    if let Some(tree) = &plan.decision_tree {
+        return evaluate_decision_node(tree, value, &mut slots);
+    }
+    if let Some(dispatch) = &plan.dispatch {
+        let tag = value.and_then(|source| field(source, &dispatch.key));
+        let candidates = tag
+            .and_then(|tag| {
+                dispatch
+                    .cases
+                    .iter()
+                    .find(|case| values_equal(tag, &case.value))
+            })
+            .map_or(&dispatch.fallback, |case| &case.branches);
+        return candidates.iter().copied().find(|branch_index| {
+            evaluate_predicate(&plan.branches[*branch_index].predicate, value, &mut slots)
+        });
+    }
+
+    plan.branches
+        .iter()
+        .position(|branch| evaluate_predicate(&branch.predicate, value, &mut slots))
+}
+
+pub fn evaluate_predicate<S>(
+    predicate: &CompiledPredicate,
+    value: Option<&Value>,
+    slots: &mut S,
+) -> bool
+where
+    S: FnMut(u32, Option<&Value>) -> bool,
+{
+    match predicate {
+        CompiledPredicate::TypeOf { value: type_name } => type_name_of(value) == Some(type_name),
+        CompiledPredicate::IsNull => matches!(value, Some(Value::Null)),
+        CompiledPredicate::IsUndefined => value.is_none(),
+        CompiledPredicate::Eq { value: expected } => {
+            value.is_some_and(|actual| values_equal(actual, expected))
+        }
+        CompiledPredicate::TagEq {
+            key,
+            value: expected,
+        } => value
+            .and_then(|source| field(source, key))
+            .is_some_and(|actual| values_equal(actual, expected)),
+        CompiledPredicate::OneOf { values } => {
+            value.is_some_and(|actual| values.iter().any(|expected| values_equal(actual, expected)))
+        }
+        CompiledPredicate::StartsWith { value: prefix } => {
+            string_of(value).is_some_and(|text| text.starts_with(prefix.as_str()))
+        }
+        CompiledPredicate::EndsWith { value: suffix } => {
+            string_of(value).is_some_and(|text| text.ends_with(suffix.as_str()))
+        }
+        CompiledPredicate::Contains { value: needle } => {
+            string_of(value).is_some_and(|text| text.contains(needle.as_str()))
+        }
+        CompiledPredicate::Regex { pattern, flags } => string_of(value)
+            .is_some_and(|text| regex_matches(pattern, flags.as_deref(), text).unwrap_or(false)),
+        CompiledPredicate::Range {
+            min,
+            max,
+            min_inclusive,
+            max_inclusive,
+        } => value
+            .and_then(Value::as_f64)
+            .is_some_and(|number| in_range(number, *min, *max, *min_inclusive, *max_inclusive)),
+        CompiledPredicate::Shape { fields, exact } => {
+            let Some(source) = value.filter(|source| is_object_like(source)) else {
+                return false;
+            };
+            let partial_match = fields
+                .iter()
+                .all(|(key, node)| evaluate_predicate(node, field(source, key), slots));
+            if !partial_match {
+                return false;
+            }
+            !*exact || key_count(source) == fields.len()
+        }
+        CompiledPredicate::Tuple { items, rest } => {
+            let Some(Value::Array(elements)) = value else {
+                return false;
+            };
+            let length_matches = match rest {
+                Some(_) => elements.len() >= items.len(),
+                None => elements.len() == items.len(),
+            };
+            length_matches
+                && items
+                    .iter()
+                    .zip(elements)
+                    .all(|(node, element)| evaluate_predicate(node, Some(element), slots))
+                && rest.as_deref().is_none_or(|node| {
+                    elements[items.len()..]
+                        .iter()
+                        .all(|element| evaluate_predicate(node, Some(element), slots))
+                })
+        }
+        CompiledPredicate::ArrayOf { predicate } => match value {
+            Some(Value::Array(elements)) => elements
+                .iter()
+                .all(|element| evaluate_predicate(predicate, Some(element), slots)),
+            _ => false,
+        },
+        CompiledPredicate::ArrayLength { length } => {
+            matches!(value, Some(Value::Array(elements)) if elements.len() == *length)
+        }
+        CompiledPredicate::And { predicates } => predicates
+            .iter()
+            .all(|node| evaluate_predicate(node, value, slots)),
+        CompiledPredicate::Or { predicates } => predicates
+            .iter()
+            .any(|node| evaluate_predicate(node, value, slots)),
+        CompiledPredicate::Not { predicate } => !evaluate_predicate(predicate, value, slots),
+        CompiledPredicate::Slot { slot } => slots(*slot, value),
+        CompiledPredicate::True => true,
+    }
+}
+
+fn evaluate_decision_node<S>(
+    node: &DecisionNode,
+    value: Option<&Value>,
+    slots: &mut S,
+) -> Option<usize>
+where
+    S: FnMut(u32, Option<&Value>) -> bool,
+{
+    match node {
+        DecisionNode::Switch {
+            test,
+            cases,
+            default,
+        } => {
+            let discriminant = decision_value(test, value);
+            let next = discriminant
+                .and_then(|actual| cases.iter().find(|case| values_equal(&actual, &case.value)))
+                .map(|case| &case.node)
+                .unwrap_or(default);
+            evaluate_decision_node(next, value, slots)
+        }
+        DecisionNode::Leaf { candidates } => candidates
+            .iter()
+            .find(|candidate| evaluate_predicate(&candidate.predicate, value, slots))
+            .map(|candidate| candidate.branch_index),
+    }
+}
+
+fn string_of(value: Option<&Value>) -> Option<&str> {
+    value.and_then(Value::as_str)
+}
+
+// Only the JS flags with a `regex` inline equivalent are honored; `g`, `y` and
+// `u` do not change whether a single test matches.
+pub(crate) fn compile_regex(pattern: &str, flags: Option<&str>) -> Result<Regex, regex::Error> {
+    let inline: String = flags
+        .unwrap_or_default()
+        .chars()
+        .filter(|flag| matches!(flag, 'i' | 'm' | 's'))
+        .collect();
+    if inline.is_empty() {
+        Regex::new(pattern)
+    } else {
+        Regex::new(&format!("(?{}){}", inline, pattern))
+    }
+}
+
+thread_local! {
+    static REGEX_CACHE: RefCell<HashMap<(String, String), Option<Regex>>> =
+        RefCell::new(HashMap::new());
+}
+
+// `None` when the pattern does not compile.
+pub(crate) fn regex_matches(pattern: &str, flags: Option<&str>, text: &str) -> Option<bool> {
+    REGEX_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let key = (pattern.to_string(), flags.unwrap_or_default().to_string());
+        cache
+            .entry(key)
+            .or_insert_with(|| compile_regex(pattern, flags).ok())
+            .as_ref()
+            .map(|regex| regex.is_match(text))
+    })
+}
+
+pub(crate) fn in_range(
+    number: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    min_inclusive: bool,
+    max_inclusive: bool,
+) -> bool {
+    let above = min.is_none_or(|min| number > min || (min_inclusive && number == min));
+    let below = max.is_none_or(|max| number < max || (max_inclusive && number == max));
+    above && below
+}
+
+fn decision_value(test: &DecisionTest, value: Option<&Value>) -> Option<Value> {
+    match test {
+        DecisionTest::Tag { key } => value.and_then(|source| field(source, key)).cloned(),
+        DecisionTest::TypeOf => type_name_of(value).map(|name| Value::String(name.to_string())),
+        DecisionTest::IsNull => Some(Value::Bool(matches!(value, Some(Value::Null)))),
+    }
+}
+
+fn type_name_of(value: Option<&Value>) -> Option<&'static str> {
+    match value? {
+        Value::Number(_) => Some("number"),
+        Value::String(_) => Some("string"),
+        Value::Bool(_) => Some("boolean"),
+        Value::Object(_) | Value::Array(_) => Some("object"),
+        Value::Null => None,
+    }
+}
+
+pub(crate) fn is_object_like(value: &Value) -> bool {
+    matches!(value, Value::Object(_) | Value::Array(_))
+}
+
+// Arrays behave like JS objects keyed by their indices.
+pub(crate) fn field<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => parse_index(key).and_then(|index| items.get(index)),
+        _ => None,
+    }
+}
+
+fn parse_index(key: &str) -> Option<usize> {
+    let is_canonical = key == "0" || (!key.starts_with('0') && !key.is_empty());
+    if !is_canonical || !key.chars().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+    key.parse().ok()
+}
+
+pub(crate) fn key_count(value: &Value) -> usize {
+    match value {
+        Value::Object(map) => map.len(),
+        Value::Array(items) => items.len(),
+        _ => 0,
+    }
+}
+
+// Mirrors the TS `deepEqual`: numbers compare by value, so `1` equals `1.0`.
+pub(crate) fn values_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64() == right.as_f64(),
+        (Value::Array(left), Value::Array(right)) => {
+            left.len() == right.len()
+                && left
+                    .iter()
+                    .zip(right)
+                    .all(|(left, right)| values_equal(left, right))
+        }
+        (Value::Object(left), Value::Object(right)) => {
+            left.len() == right.len()
+                && left.iter().all(|(key, left)| {
+                    right
+                        .get(key)
+                        .is_some_and(|right| values_equal(left, right))
+                })
+        }
+        _ => left == right,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{
+        CompileOptions, MatchProgram, PredicateAst, compile_program, compile_program_with_options,
+        tests::branch,
+    };
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn router() -> MatchProgram {
+        let mut fields = BTreeMap::new();
+        fields.insert("amount".to_string(), PredicateAst::Slot { slot: 0 });
+        MatchProgram {
+            branches: vec![
+                branch(PredicateAst::And {
+                    predicates: vec![
+                        PredicateAst::Tag {
+                            key: "type".to_string(),
+                            value: json!("pay"),
+                        },
+                        PredicateAst::Shape {
+                            fields,
+                            exact: false,
+                        },
+                    ],
+                }),
+                branch(PredicateAst::Tag {
+                    key: "type".to_string(),
+                    value: json!("refund"),
+                }),
+                branch(PredicateAst::IsNumber),
+                branch(PredicateAst::Wildcard),
+            ],
+            input_schema: None,
+        }
+    }
+
+    fn positive_amount(slot: u32, value: Option<&Value>) -> bool {
+        slot == 0
+            && value
+                .and_then(Value::as_f64)
+                .is_some_and(|amount| amount > 0.0)
+    }
+
+    #[test]
+    fn test_evaluate_plan_in_branch_order() {
+        let plan = compile_program(&router());
+
+        let cases = [
+            (json!({ "type": "pay", "amount": 5 }), Some(0)),
+            (json!({ "type": "pay", "amount": -1 }), Some(3)),
+            (json!({ "type": "refund" }), Some(1)),
+            (json!(12.5), Some(2)),
+            (json!("other"), Some(3)),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(
+                evaluate_plan(&plan, Some(&value), positive_amount),
+                expected,
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_decision_tree_agrees_with_linear_scan() {
+        let program = router();
+        let linear = compile_program(&program);
+        let tree = compile_program_with_options(
+            &program,
+            &CompileOptions {
+                decision_tree: true,
+                ..CompileOptions::default()
+            },
+        );
+
+        let values = [
+            json!({ "type": "pay", "amount": 5 }),
+            json!({ "type": "pay" }),
+            json!({ "type": "refund", "amount": 1 }),
+            json!([1, 2]),
+            json!(null),
+            json!(3),
+        ];
+        for value in &values {
+            assert_eq!(
+                evaluate_plan(&linear, Some(value), positive_amount),
+                evaluate_plan(&tree, Some(value), positive_amount),
+                "{}",
+                value
+            );
+        }
+        assert_eq!(evaluate_plan(&tree, None, positive_amount), Some(3));
+    }
+
+    #[test]
+    fn test_dispatch_agrees_with_linear_scan() {
+        let mut program = router();
+        program.branches.remove(2);
+        let linear = compile_program(&program);
+        let dispatched = compile_program_with_options(
+            &program,
+            &CompileOptions {
+                dispatch: true,
+                ..CompileOptions::default()
+            },
+        );
+        assert!(dispatched.dispatch.is_some());
+
+        let values = [
+            json!({ "type": "pay", "amount": 5 }),
+            json!({ "type": "pay", "amount": -5 }),
+            json!({ "type": "refund" }),
+            json!({ "type": "other" }),
+            json!(3),
+        ];
+        for value in &values {
+            assert_eq!(
+                evaluate_plan(&linear, Some(value), positive_amount),
+                evaluate_plan(&dispatched, Some(value), positive_amount),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_exact_shape_and_undefined_fields() {
+        let exact_fields = BTreeMap::from([("id".to_string(), PredicateAst::IsNumber)]);
+        let open_fields = BTreeMap::from([("note".to_string(), PredicateAst::IsUndefined)]);
+        let plan = compile_program(&MatchProgram {
+            branches: vec![
+                branch(PredicateAst::Shape {
+                    fields: exact_fields,
+                    exact: true,
+                }),
+                branch(PredicateAst::Shape {
+                    fields: open_fields,
+                    exact: false,
+                }),
+            ],
+            input_schema: None,
+        });
+
+        let no_slots = |_: u32, _: Option<&Value>| false;
+        assert_eq!(
+            evaluate_plan(&plan, Some(&json!({ "id": 1 })), no_slots),
+            Some(0)
+        );
+        assert_eq!(
+            evaluate_plan(&plan, Some(&json!({ "id": 1, "extra": true })), no_slots),
+            Some(1)
+        );
+        assert_eq!(
+            evaluate_plan(&plan, Some(&json!({ "id": 1, "note": "x" })), no_slots),
+            None
+        );
+    }
+
+    #[test]
+    fn test_range_bounds() {
+        let range = CompiledPredicate::Range {
+            min: Some(0.0),
+            max: Some(10.0),
+            min_inclusive: false,
+            max_inclusive: true,
+        };
+        let mut no_slots = |_: u32, _: Option<&Value>| false;
+
+        assert!(!evaluate_predicate(&range, Some(&json!(0)), &mut no_slots));
+        assert!(evaluate_predicate(&range, Some(&json!(0.5)), &mut no_slots));
+        assert!(evaluate_predicate(&range, Some(&json!(10)), &mut no_slots));
+        assert!(!evaluate_predicate(
+            &range,
+            Some(&json!("5")),
+            &mut no_slots
+        ));
+    }
+
+    #[test]
+    fn test_array_patterns() {
+        let command = CompiledPredicate::Tuple {
+            items: vec![CompiledPredicate::Eq {
+                value: json!("add"),
+            }],
+            rest: Some(Box::new(CompiledPredicate::TypeOf {
+                value: "number".to_string(),
+            })),
+        };
+        let mut no_slots = |_: u32, _: Option<&Value>| false;
+
+        assert!(evaluate_predicate(
+            &command,
+            Some(&json!(["add"])),
+            &mut no_slots
+        ));
+        assert!(evaluate_predicate(
+            &command,
+            Some(&json!(["add", 1, 2])),
+            &mut no_slots
+        ));
+        assert!(!evaluate_predicate(
+            &command,
+            Some(&json!(["add", "x"])),
+            &mut no_slots
+        ));
+        assert!(!evaluate_predicate(
+            &command,
+            Some(&json!({ "0": "add" })),
+            &mut no_slots
+        ));
+        assert!(evaluate_predicate(
+            &CompiledPredicate::ArrayLength { length: 2 },
+            Some(&json!([1, 2])),
+            &mut no_slots
+        ));
+    }
+
+    #[test]
+    fn test_string_predicates() {
+        let mut no_slots = |_: u32, _: Option<&Value>| false;
+        let event = json!("Order.Paid");
+        let regex = |flags: Option<&str>| CompiledPredicate::Regex {
+            pattern: "^order\\.(paid|settled)$".to_string(),
+            flags: flags.map(str::to_string),
+        };
+
+        assert!(evaluate_predicate(
+            &CompiledPredicate::EndsWith {
+                value: ".Paid".to_string()
+            },
+            Some(&event),
+            &mut no_slots
+        ));
+        assert!(!evaluate_predicate(
+            &regex(None),
+            Some(&event),
+            &mut no_slots
+        ));
+        assert!(evaluate_predicate(
+            &regex(Some("i")),
+            Some(&event),
+            &mut no_slots
+        ));
+        assert!(!evaluate_predicate(
+            &CompiledPredicate::Contains {
+                value: "1".to_string()
+            },
+            Some(&json!(1)),
+            &mut no_slots
+        ));
+    }
+
+    #[test]
+    fn test_values_equal_compares_numbers_by_value() {
+        assert!(values_equal(&json!({ "n": [1] }), &json!({ "n": [1.0] })));
+        assert!(!values_equal(&json!([1]), &json!({ "0": 1 })));
+    }
+
+    #[test]
+    fn test_bindings_capture_by_path() {
+        let bind = |name: &str, predicate: PredicateAst| PredicateAst::Bind {
+            name: name.to_string(),
+            predicate: Box::new(predicate),
+        };
+        let program = MatchProgram {
+            branches: vec![
+                branch(PredicateAst::Shape {
+                    fields: BTreeMap::from([
+                        (
+                            "type".to_string(),
+                            PredicateAst::Eq {
+                                value: json!("pay"),
+                            },
+                        ),
+                        (
+                            "orderId".to_string(),
+                            bind("orderId", PredicateAst::IsString),
+                        ),
+                        (
+                            "lines".to_string(),
+                            PredicateAst::Tuple {
+                                items: vec![bind("first", PredicateAst::Wildcard)],
+                                rest: Some(Box::new(PredicateAst::Wildcard)),
+                            },
+                        ),
+                    ]),
+                    exact: false,
+                }),
+                branch(bind("other", PredicateAst::Wildcard)),
+            ],
+            input_schema: None,
+        };
+        let plan = compile_program(&program);
+
+        let value = json!({ "type": "pay", "orderId": "o-1", "lines": [{ "sku": "a" }] });
+        let matched = evaluate_plan_with_bindings(&plan, Some(&value), |_, _| false).unwrap();
+        assert_eq!(matched.action_index, 0);
+        assert_eq!(
+            matched.bindings,
+            BTreeMap::from([
+                ("orderId".to_string(), json!("o-1")),
+                ("first".to_string(), json!({ "sku": "a" })),
+            ])
+        );
+
+        let matched = evaluate_plan_with_bindings(&plan, None, |_, _| false).unwrap();
+        assert_eq!(matched.action_index, 1);
+        assert!(matched.bindings.is_empty());
+    }
+}
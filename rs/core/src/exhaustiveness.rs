@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+
+use crate::compiler::{CompiledBranch, CompiledPredicate};
+use crate::evaluator::{evaluate_predicate, field, is_object_like, key_count, values_equal};
+
+const MAX_WITNESSES: usize = 4096;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum InputSchema {
+    Number,
+    String,
+    Boolean,
+    Null,
+    Undefined,
+    Literal {
+        value: Value,
+    },
+    Object {
+        fields: BTreeMap<String, InputSchema>,
+    },
+    Union {
+        variants: Vec<InputSchema>,
+    },
+}
+
+// Returns the first input described by `schema` that every branch provably rejects.
+// `Some(None)` means the counterexample is `undefined`.
+pub fn find_unhandled_input(
+    schema: &InputSchema,
+    branches: &[CompiledBranch],
+) -> Option<Option<Value>> {
+    let mut constants = Constants::default();
+    for branch in branches {
+        constants.collect(&branch.predicate);
+    }
+
+    witnesses(schema, &constants).into_iter().find(|witness| {
+        branches
+            .iter()
+            .all(|branch| may_match(&branch.predicate, witness.as_ref()) == Some(false))
+    })
+}
+
+#[derive(Debug, Default)]
+struct Constants {
+    numbers: Vec<Value>,
+    strings: Vec<Value>,
+}
+
+impl Constants {
+    fn collect(&mut self, predicate: &CompiledPredicate) {
+        match predicate {
+            CompiledPredicate::Eq { value } | CompiledPredicate::TagEq { value, .. } => {
+                self.push(value)
+            }
+            CompiledPredicate::OneOf { values } => values.iter().for_each(|value| self.push(value)),
+            CompiledPredicate::StartsWith { value }
+            | CompiledPredicate::EndsWith { value }
+            | CompiledPredicate::Contains { value } => self.push(&Value::String(value.clone())),
+            CompiledPredicate::Range { min, max, .. } => {
+                // Probe each bound and one step to either side of it.
+                for bound in [min, max].into_iter().flatten() {
+                    for number in [bound - 1.0, *bound, bound + 1.0] {
+                        if let Some(number) = number_value(number) {
+                            self.push(&number);
+                        }
+                    }
+                }
+            }
+            CompiledPredicate::Shape { fields, .. } => {
+                fields.values().for_each(|node| self.collect(node))
+            }
+            CompiledPredicate::And { predicates } | CompiledPredicate::Or { predicates } => {
+                predicates.iter().for_each(|node| self.collect(node))
+            }
+            CompiledPredicate::Not { predicate } | CompiledPredicate::ArrayOf { predicate } => {
+                self.collect(predicate)
+            }
+            CompiledPredicate::Tuple { items, rest } => items
+                .iter()
+                .chain(rest.as_deref())
+                .for_each(|node| self.collect(node)),
+            _ => {}
+        }
+    }
+
+    fn push(&mut self, value: &Value) {
+        let target = match value {
+            Value::Number(_) => &mut self.numbers,
+            Value::String(_) => &mut self.strings,
+            _ => return,
+        };
+        if !target.iter().any(|item| values_equal(item, value)) {
+            target.push(value.clone());
+        }
+    }
+
+    fn fresh_number(&self) -> Value {
+        let mut candidate = 0u64;
+        while self
+            .numbers
+            .iter()
+            .any(|item| item.as_f64() == Some(candidate as f64))
+        {
+            candidate += 1;
+        }
+        Value::Number(Number::from(candidate))
+    }
+
+    fn fresh_string(&self) -> Value {
+        let mut candidate = String::new();
+        while self
+            .strings
+            .iter()
+            .any(|item| item.as_str() == Some(candidate.as_str()))
+        {
+            candidate.push('_');
+        }
+        Value::String(candidate)
+    }
+}
+
+fn number_value(number: f64) -> Option<Value> {
+    if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+        Some(Value::Number(Number::from(number as i64)))
+    } else {
+        Number::from_f64(number).map(Value::Number)
+    }
+}
+
+// A finite set of representative inputs: every constant the program compares
+// against, plus one value of each primitive type that no constant mentions.
+fn witnesses(schema: &InputSchema, constants: &Constants) -> Vec<Option<Value>> {
+    match schema {
+        InputSchema::Number => constants
+            .numbers
+            .iter()
+            .cloned()
+            .chain([constants.fresh_number()])
+            .map(Some)
+            .collect(),
+        InputSchema::String => constants
+            .strings
+            .iter()
+            .cloned()
+            .chain([constants.fresh_string()])
+            .map(Some)
+            .collect(),
+        InputSchema::Boolean => vec![Some(Value::Bool(true)), Some(Value::Bool(false))],
+        InputSchema::Null => vec![Some(Value::Null)],
+        InputSchema::Undefined => vec![None],
+        InputSchema::Literal { value } => vec![Some(value.clone())],
+        InputSchema::Union { variants } => {
+            let mut items: Vec<Option<Value>> = Vec::new();
+            for witness in variants
+                .iter()
+                .flat_map(|variant| witnesses(variant, constants))
+            {
+                if items.len() >= MAX_WITNESSES {
+                    break;
+                }
+                if !items.contains(&witness) {
+                    items.push(witness);
+                }
+            }
+            items
+        }
+        InputSchema::Object { fields } => {
+            let mut objects = vec![Map::new()];
+            for (key, field_schema) in fields {
+                let field_witnesses = witnesses(field_schema, constants);
+                objects = objects
+                    .iter()
+                    .flat_map(|object| {
+                        field_witnesses.iter().map(move |witness| {
+                            let mut next = object.clone();
+                            if let Some(value) = witness {
+                                next.insert(key.clone(), value.clone());
+                            }
+                            next
+                        })
+                    })
+                    .take(MAX_WITNESSES)
+                    .collect();
+            }
+            objects
+                .into_iter()
+                .map(|object| Some(Value::Object(object)))
+                .collect()
+        }
+    }
+}
+
+// Three-valued evaluation: `None` when the result depends on a runtime slot.
+fn may_match(predicate: &CompiledPredicate, value: Option<&Value>) -> Option<bool> {
+    match predicate {
+        CompiledPredicate::Slot { .. } => None,
+        CompiledPredicate::And { predicates } => {
+            all_of(predicates.iter().map(|node| may_match(node, value)))
+        }
+        CompiledPredicate::Or { predicates } => {
+            let results = predicates
+                .iter()
+                .map(|node| may_match(node, value).map(|result| !result));
+            all_of(results).map(|result| !result)
+        }
+        CompiledPredicate::Not { predicate } => may_match(predicate, value).map(|result| !result),
+        CompiledPredicate::Shape { fields, exact } => {
+            let Some(source) = value.filter(|source| is_object_like(source)) else {
+                return Some(false);
+            };
+            if *exact && key_count(source) != fields.len() {
+                return Some(false);
+            }
+            all_of(
+                fields
+                    .iter()
+                    .map(|(key, node)| may_match(node, field(source, key))),
+            )
+        }
+        CompiledPredicate::Tuple { items, rest } => {
+            let Some(Value::Array(elements)) = value else {
+                return Some(false);
+            };
+            let length_matches = match rest {
+                Some(_) => elements.len() >= items.len(),
+                None => elements.len() == items.len(),
+            };
+            if !length_matches {
+                return Some(false);
+            }
+            let rest_results = rest.as_deref().into_iter().flat_map(|node| {
+                elements[items.len()..]
+                    .iter()
+                    .map(move |element| may_match(node, Some(element)))
+            });
+            all_of(
+                items
+                    .iter()
+                    .zip(elements)
+                    .map(|(node, element)| may_match(node, Some(element)))
+                    .chain(rest_results),
+            )
+        }
+        CompiledPredicate::ArrayOf { predicate } => match value {
+            Some(Value::Array(elements)) => all_of(
+                elements
+                    .iter()
+                    .map(|element| may_match(predicate, Some(element))),
+            ),
+            _ => Some(false),
+        },
+        leaf => Some(evaluate_predicate(leaf, value, &mut |_, _| false)),
+    }
+}
+
+fn all_of(results: impl Iterator<Item = Option<bool>>) -> Option<bool> {
+    let mut known = true;
+    for result in results {
+        match result {
+            Some(false) => return Some(false),
+            Some(true) => {}
+            None => known = false,
+        }
+    }
+    known.then_some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{MatchProgram, PredicateAst, compile_program, tests::branch};
+    use serde_json::json;
+
+    fn tag(value: &str) -> PredicateAst {
+        PredicateAst::Tag {
+            key: "type".to_string(),
+            value: json!(value),
+        }
+    }
+
+    fn event_schema() -> InputSchema {
+        let variant = |tag: &str| InputSchema::Object {
+            fields: BTreeMap::from([
+                (
+                    "type".to_string(),
+                    InputSchema::Literal { value: json!(tag) },
+                ),
+                ("amount".to_string(), InputSchema::Number),
+            ]),
+        };
+        InputSchema::Union {
+            variants: vec![variant("pay"), variant("refund"), InputSchema::Null],
+        }
+    }
+
+    fn non_exhaustive_counterexample(program: &MatchProgram) -> Option<Option<Value>> {
+        compile_program(program)
+            .diagnostics
+            .into_iter()
+            .find(|item| item.code == "non_exhaustive")
+            .map(|item| {
+                assert_eq!(item.branch_index, None);
+                assert_eq!(item.counterexample_undefined, item.counterexample.is_none());
+                item.counterexample
+            })
+    }
+
+    #[test]
+    fn test_reports_missing_variant() {
+        let program = MatchProgram {
+            branches: vec![branch(tag("pay")), branch(PredicateAst::IsNull)],
+            input_schema: Some(event_schema()),
+        };
+
+        assert_eq!(
+            non_exhaustive_counterexample(&program),
+            Some(Some(json!({ "type": "refund", "amount": 0 })))
+        );
+    }
+
+    #[test]
+    fn test_covered_union_is_exhaustive() {
+        let program = MatchProgram {
+            branches: vec![
+                branch(tag("pay")),
+                branch(tag("refund")),
+                branch(PredicateAst::IsNull),
+            ],
+            input_schema: Some(event_schema()),
+        };
+
+        assert_eq!(non_exhaustive_counterexample(&program), None);
+    }
+
+    #[test]
+    fn test_literal_counterexample_avoids_program_constants() {
+        let program = MatchProgram {
+            branches: vec![
+                branch(PredicateAst::Eq { value: json!(0) }),
+                branch(PredicateAst::Eq { value: json!(1) }),
+            ],
+            input_schema: Some(InputSchema::Number),
+        };
+
+        assert_eq!(
+            non_exhaustive_counterexample(&program),
+            Some(Some(json!(2)))
+        );
+    }
+
+    #[test]
+    fn test_undefined_counterexample_is_flagged() {
+        let program = MatchProgram {
+            branches: vec![branch(PredicateAst::IsNull)],
+            input_schema: Some(InputSchema::Union {
+                variants: vec![InputSchema::Null, InputSchema::Undefined],
+            }),
+        };
+
+        assert_eq!(non_exhaustive_counterexample(&program), Some(None));
+        let diagnostic = serde_json::to_value(&compile_program(&program).diagnostics[0]).unwrap();
+        assert_eq!(diagnostic["counterexampleUndefined"], json!(true));
+        assert!(diagnostic.get("branchIndex").is_none());
+        assert!(diagnostic.get("counterexample").is_none());
+    }
+
+    #[test]
+    fn test_slot_branches_are_not_counted_as_rejecting() {
+        let program = MatchProgram {
+            branches: vec![branch(PredicateAst::And {
+                predicates: vec![tag("pay"), PredicateAst::Slot { slot: 0 }],
+            })],
+            input_schema: Some(InputSchema::Object {
+                fields: BTreeMap::from([(
+                    "type".to_string(),
+                    InputSchema::Literal {
+                        value: json!("pay"),
+                    },
+                )]),
+            }),
+        };
+
+        assert_eq!(non_exhaustive_counterexample(&program), None);
+    }
+}
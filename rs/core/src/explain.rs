@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::compiler::{CompilePlan, CompiledPredicate};
+use crate::evaluator::{evaluate_predicate, field, is_object_like, key_count};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MatchTrace {
+    #[serde(rename = "branchIndex")]
+    pub branch_index: Option<usize>,
+    #[serde(rename = "actionIndex")]
+    pub action_index: Option<usize>,
+    pub branches: Vec<BranchTrace>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BranchTrace {
+    #[serde(rename = "branchIndex")]
+    pub branch_index: usize,
+    #[serde(rename = "actionIndex")]
+    pub action_index: usize,
+    pub matched: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<PredicateFailure>,
+}
+
+// `path` leads from the input to the value the failing test read, and `actual`
+// is that value (`None` when it is undefined).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PredicateFailure {
+    pub path: Vec<String>,
+    pub predicate: CompiledPredicate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actual: Option<Value>,
+}
+
+// Evaluates every branch, not just those before the first match, so slots may be
+// called more often than `evaluate_plan` would call them.
+pub fn explain_plan<S>(plan: &CompilePlan, value: Option<&Value>, mut slots: S) -> MatchTrace
+where
+    S: FnMut(u32, Option<&Value>) -> bool,
+{
+    let branches: Vec<BranchTrace> = plan
+        .branches
+        .iter()
+        .enumerate()
+        .map(|(branch_index, branch)| {
+            let failure = explain_predicate(&branch.predicate, value, &[], &mut slots).err();
+            BranchTrace {
+                branch_index,
+                action_index: branch.action_index,
+                matched: failure.is_none(),
+                failure,
+            }
+        })
+        .collect();
+
+    let selected = branches.iter().find(|branch| branch.matched);
+    MatchTrace {
+        branch_index: selected.map(|branch| branch.branch_index),
+        action_index: selected.map(|branch| branch.action_index),
+        branches,
+    }
+}
+
+fn explain_predicate<S>(
+    predicate: &CompiledPredicate,
+    value: Option<&Value>,
+    path: &[String],
+    slots: &mut S,
+) -> Result<(), PredicateFailure>
+where
+    S: FnMut(u32, Option<&Value>) -> bool,
+{
+    let fail = |predicate: &CompiledPredicate, path: &[String], actual: Option<&Value>| {
+        Err(PredicateFailure {
+            path: path.to_vec(),
+            predicate: predicate.clone(),
+            actual: actual.cloned(),
+        })
+    };
+
+    match predicate {
+        CompiledPredicate::And { predicates } => predicates
+            .iter()
+            .try_for_each(|node| explain_predicate(node, value, path, slots)),
+        CompiledPredicate::Shape { fields, exact } => {
+            let Some(source) = value.filter(|source| is_object_like(source)) else {
+                return fail(predicate, path, value);
+            };
+            for (key, node) in fields {
+                explain_predicate(node, field(source, key), &child(path, key), slots)?;
+            }
+            if *exact && key_count(source) != fields.len() {
+                return fail(predicate, path, value);
+            }
+            Ok(())
+        }
+        CompiledPredicate::TagEq { key, .. } => {
+            let actual = value.and_then(|source| field(source, key));
+            if evaluate_predicate(predicate, value, slots) {
+                Ok(())
+            } else {
+                fail(predicate, &child(path, key), actual)
+            }
+        }
+        CompiledPredicate::Tuple { items, rest } => {
+            let Some(Value::Array(elements)) = value else {
+                return fail(predicate, path, value);
+            };
+            let length_matches = match rest {
+                Some(_) => elements.len() >= items.len(),
+                None => elements.len() == items.len(),
+            };
+            if !length_matches {
+                return fail(predicate, path, value);
+            }
+            for (index, element) in elements.iter().enumerate() {
+                let node = items.get(index).or(rest.as_deref());
+                if let Some(node) = node {
+                    explain_predicate(
+                        node,
+                        Some(element),
+                        &child(path, &index.to_string()),
+                        slots,
+                    )?;
+                }
+            }
+            Ok(())
+        }
+        CompiledPredicate::ArrayOf { predicate: element } => {
+            let Some(Value::Array(elements)) = value else {
+                return fail(predicate, path, value);
+            };
+            elements.iter().enumerate().try_for_each(|(index, item)| {
+                explain_predicate(element, Some(item), &child(path, &index.to_string()), slots)
+            })
+        }
+        // `or` and `not` have no single failing child, so the node itself is reported.
+        _ if evaluate_predicate(predicate, value, slots) => Ok(()),
+        _ => fail(predicate, path, value),
+    }
+}
+
+fn child(path: &[String], key: &str) -> Vec<String> {
+    let mut next = path.to_vec();
+    next.push(key.to_string());
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{MatchProgram, PredicateAst, compile_program, tests::branch};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_reports_failing_leaf_with_path() {
+        let program = MatchProgram {
+            branches: vec![
+                branch(PredicateAst::Tag {
+                    key: "type".to_string(),
+                    value: json!("pay"),
+                }),
+                branch(PredicateAst::Shape {
+                    fields: BTreeMap::from([(
+                        "order".to_string(),
+                        PredicateAst::Shape {
+                            fields: BTreeMap::from([
+                                ("id".to_string(), PredicateAst::IsString),
+                                (
+                                    "amount".to_string(),
+                                    PredicateAst::Range {
+                                        min: Some(0.0),
+                                        max: None,
+                                        min_inclusive: false,
+                                        max_inclusive: false,
+                                    },
+                                ),
+                            ]),
+                            exact: false,
+                        },
+                    )]),
+                    exact: false,
+                }),
+                branch(PredicateAst::Wildcard),
+            ],
+            input_schema: None,
+        };
+        let plan = compile_program(&program);
+        let value = json!({ "type": "refund", "order": { "id": "o-1", "amount": -5 } });
+
+        let trace = explain_plan(&plan, Some(&value), |_, _| false);
+        assert_eq!(trace.branch_index, Some(2));
+        assert_eq!(
+            trace.branches[0].failure,
+            Some(PredicateFailure {
+                path: vec!["type".to_string()],
+                predicate: plan.branches[0].predicate.clone(),
+                actual: Some(json!("refund")),
+            })
+        );
+        let failure = trace.branches[1].failure.as_ref().unwrap();
+        assert_eq!(
+            failure.path,
+            vec!["order".to_string(), "amount".to_string()]
+        );
+        assert!(matches!(failure.predicate, CompiledPredicate::Range { .. }));
+        assert_eq!(failure.actual, Some(json!(-5)));
+        assert!(trace.branches[2].matched);
+    }
+}
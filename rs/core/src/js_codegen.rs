@@ -0,0 +1,465 @@
+use serde_json::Value;
+
+use crate::compiler::{CompilePlan, CompiledBranch, CompiledPredicate};
+
+// Defined once, outside the returned matcher. `field` only reads own properties, and never an
+// array's `length`, so that it agrees with the native evaluator on missing fields.
+const PRELUDE: &str = r#"  const isObject = (item) => item !== null && typeof item === "object";
+  const field = (item, key) =>
+    isObject(item) && !(Array.isArray(item) && key === "length") && Object.prototype.hasOwnProperty.call(item, key)
+      ? item[key]
+      : undefined;
+  const deepEqual = (left, right) => {
+    if (left === right) return true;
+    if (!isObject(left) || !isObject(right) || Array.isArray(left) !== Array.isArray(right)) {
+      return false;
+    }
+    const keys = Object.keys(left);
+    return (
+      keys.length === Object.keys(right).length &&
+      keys.every((key) => Object.prototype.hasOwnProperty.call(right, key) && deepEqual(left[key], right[key]))
+    );
+  };
+  const callSlot = (slots, slot, input) => {
+    const fn = slots == null ? undefined : typeof slots.get === "function" ? slots.get(slot) : slots[slot];
+    return fn ? Boolean(fn(input)) : false;
+  };
+"#;
+
+// Emits JS source for an expression that evaluates to `(value, slots) => actionIndex`,
+// returning -1 when no branch matches. `slots` may be a `Map` or an array of
+// `(value) => boolean` callbacks. The helpers and regexes live in an enclosing IIFE,
+// so they are built once. A `dispatch` table with scalar tags becomes a `switch`;
+// `decisionTree` is ignored.
+pub fn generate_js(plan: &CompilePlan) -> String {
+    let mut generator = JsGenerator::default();
+    let body = match &plan.dispatch {
+        Some(dispatch) if dispatch.cases.iter().all(|case| is_scalar(&case.value)) => {
+            let mut body = format!("  switch (field(value, {})) {{\n", js_string(&dispatch.key));
+            for case in &dispatch.cases {
+                body.push_str(&format!("    case {}:\n", case.value));
+                body.push_str(&generator.branch_checks(&plan.branches, &case.branches, "      "));
+                body.push_str("      return -1;\n");
+            }
+            body.push_str("    default:\n");
+            body.push_str(&generator.branch_checks(&plan.branches, &dispatch.fallback, "      "));
+            body.push_str("      return -1;\n  }\n");
+            body
+        }
+        _ => {
+            let all: Vec<usize> = (0..plan.branches.len()).collect();
+            let mut body = generator.branch_checks(&plan.branches, &all, "  ");
+            body.push_str("  return -1;\n");
+            body
+        }
+    };
+
+    let regexes: String = generator
+        .regexes
+        .iter()
+        .enumerate()
+        .map(|(index, (pattern, flags))| {
+            format!(
+                "  const regex{} = new RegExp({}, {});\n",
+                index,
+                js_string(pattern),
+                js_string(flags)
+            )
+        })
+        .collect();
+    let body: String = body.lines().map(|line| format!("  {}\n", line)).collect();
+    format!(
+        "(() => {{\n{}{}  return (value, slots) => {{\n{}  }};\n}})()",
+        PRELUDE, regexes, body
+    )
+}
+
+#[derive(Debug, Default)]
+struct JsGenerator {
+    regexes: Vec<(String, String)>,
+    depth: usize,
+}
+
+impl JsGenerator {
+    fn branch_checks(
+        &mut self,
+        branches: &[CompiledBranch],
+        indices: &[usize],
+        indent: &str,
+    ) -> String {
+        indices
+            .iter()
+            .map(|index| {
+                let branch = &branches[*index];
+                format!(
+                    "{}if ({}) return {};\n",
+                    indent,
+                    self.predicate(&branch.predicate, "value"),
+                    branch.action_index
+                )
+            })
+            .collect()
+    }
+
+    // A JS boolean expression testing the value that `target` evaluates to.
+    fn predicate(&mut self, predicate: &CompiledPredicate, target: &str) -> String {
+        match predicate {
+            CompiledPredicate::TypeOf { value } => match value.as_str() {
+                "number" | "string" | "boolean" => {
+                    format!("typeof {} === {}", target, js_string(value))
+                }
+                "object" => format!("isObject({})", target),
+                _ => "false".to_string(),
+            },
+            CompiledPredicate::IsNull => format!("{} === null", target),
+            CompiledPredicate::IsUndefined => format!("{} === undefined", target),
+            CompiledPredicate::Eq { value } => equals(target, value),
+            CompiledPredicate::TagEq { key, value } => {
+                equals(&format!("field({}, {})", target, js_string(key)), value)
+            }
+            CompiledPredicate::OneOf { values } if values.iter().all(is_scalar) => {
+                format!("{}.includes({})", Value::Array(values.clone()), target)
+            }
+            CompiledPredicate::OneOf { values } => format!(
+                "{}.some((item) => deepEqual({}, item))",
+                Value::Array(values.clone()),
+                target
+            ),
+            CompiledPredicate::StartsWith { value } => string_method(target, "startsWith", value),
+            CompiledPredicate::EndsWith { value } => string_method(target, "endsWith", value),
+            CompiledPredicate::Contains { value } => string_method(target, "includes", value),
+            CompiledPredicate::Regex { pattern, flags } => {
+                // `g` and `y` make `test` stateful; the native evaluator ignores them too.
+                let flags: String = flags
+                    .as_deref()
+                    .unwrap_or("")
+                    .chars()
+                    .filter(|flag| !matches!(flag, 'g' | 'y'))
+                    .collect();
+                let index = self.regexes.len();
+                self.regexes.push((pattern.clone(), flags));
+                format!(
+                    "(typeof {} === \"string\" && regex{}.test({}))",
+                    target, index, target
+                )
+            }
+            CompiledPredicate::Range {
+                min,
+                max,
+                min_inclusive,
+                max_inclusive,
+            } => {
+                let mut parts = vec![format!("typeof {} === \"number\"", target)];
+                if let Some(min) = min {
+                    let operator = if *min_inclusive { ">=" } else { ">" };
+                    parts.push(format!("{} {} {}", target, operator, js_number(*min)));
+                }
+                if let Some(max) = max {
+                    let operator = if *max_inclusive { "<=" } else { "<" };
+                    parts.push(format!("{} {} {}", target, operator, js_number(*max)));
+                }
+                format!("({})", parts.join(" && "))
+            }
+            CompiledPredicate::Shape { fields, exact } => {
+                let mut parts = vec![format!("isObject({})", target)];
+                for (key, node) in fields {
+                    let child = format!("field({}, {})", target, js_string(key));
+                    parts.push(self.predicate(node, &child));
+                }
+                if *exact {
+                    parts.push(format!(
+                        "Object.keys({}).length === {}",
+                        target,
+                        fields.len()
+                    ));
+                }
+                format!("({})", parts.join(" && "))
+            }
+            CompiledPredicate::Tuple { items, rest } => {
+                let length = match rest {
+                    Some(_) => format!("{}.length >= {}", target, items.len()),
+                    None => format!("{}.length === {}", target, items.len()),
+                };
+                let mut parts = vec![format!("Array.isArray({})", target), length];
+                for (index, node) in items.iter().enumerate() {
+                    let child = format!("{}[{}]", target, index);
+                    parts.push(self.predicate(node, &child));
+                }
+                if let Some(rest) = rest {
+                    let element = self.element(rest);
+                    parts.push(format!(
+                        "{}.slice({}).every({})",
+                        target,
+                        items.len(),
+                        element
+                    ));
+                }
+                format!("({})", parts.join(" && "))
+            }
+            CompiledPredicate::ArrayOf { predicate } => {
+                let element = self.element(predicate);
+                format!(
+                    "(Array.isArray({}) && {}.every({}))",
+                    target, target, element
+                )
+            }
+            CompiledPredicate::ArrayLength { length } => format!(
+                "(Array.isArray({}) && {}.length === {})",
+                target, target, length
+            ),
+            CompiledPredicate::And { predicates } => {
+                self.junction(predicates, target, " && ", "true")
+            }
+            CompiledPredicate::Or { predicates } => {
+                self.junction(predicates, target, " || ", "false")
+            }
+            CompiledPredicate::Not { predicate } => format!("!{}", self.grouped(predicate, target)),
+            CompiledPredicate::Slot { slot } => format!("callSlot(slots, {}, {})", slot, target),
+            CompiledPredicate::True => "true".to_string(),
+        }
+    }
+
+    fn junction(
+        &mut self,
+        predicates: &[CompiledPredicate],
+        target: &str,
+        operator: &str,
+        empty: &str,
+    ) -> String {
+        if predicates.is_empty() {
+            return empty.to_string();
+        }
+        let parts: Vec<String> = predicates
+            .iter()
+            .map(|node| self.predicate(node, target))
+            .collect();
+        format!("({})", parts.join(operator))
+    }
+
+    fn grouped(&mut self, predicate: &CompiledPredicate, target: &str) -> String {
+        let expression = self.predicate(predicate, target);
+        if expression.starts_with('(') && expression.ends_with(')') {
+            expression
+        } else {
+            format!("({})", expression)
+        }
+    }
+
+    // An arrow function testing one array element.
+    fn element(&mut self, predicate: &CompiledPredicate) -> String {
+        let name = format!("element{}", self.depth);
+        self.depth += 1;
+        let body = self.predicate(predicate, &name);
+        self.depth -= 1;
+        format!("({}) => {}", name, body)
+    }
+}
+
+fn equals(target: &str, value: &Value) -> String {
+    if is_scalar(value) {
+        format!("{} === {}", target, value)
+    } else {
+        format!("deepEqual({}, {})", target, value)
+    }
+}
+
+fn string_method(target: &str, method: &str, value: &str) -> String {
+    format!(
+        "(typeof {} === \"string\" && {}.{}({}))",
+        target,
+        target,
+        method,
+        js_string(value)
+    )
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+fn js_string(value: &str) -> String {
+    Value::String(value.to_string()).to_string()
+}
+
+fn js_number(number: f64) -> String {
+    if number.is_finite() {
+        number.to_string()
+    } else if number.is_nan() {
+        "NaN".to_string()
+    } else if number > 0.0 {
+        "Infinity".to_string()
+    } else {
+        "-Infinity".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{
+        BranchAst, CompileOptions, MatchProgram, PredicateAst, compile_program_with_options,
+        tests::branch,
+    };
+    use crate::dsl::parse_program_text;
+    use crate::evaluator::evaluate_plan;
+    use serde_json::json;
+    use std::process::Command;
+
+    fn tag_branch(value: &str) -> BranchAst {
+        branch(PredicateAst::Tag {
+            key: "type".to_string(),
+            value: json!(value),
+        })
+    }
+
+    #[test]
+    fn test_generates_linear_checks() {
+        let program = MatchProgram {
+            branches: vec![tag_branch("pay"), branch(PredicateAst::Slot { slot: 0 })],
+            input_schema: None,
+        };
+        let source = generate_js(&compile_program_with_options(
+            &program,
+            &CompileOptions::default(),
+        ));
+
+        assert!(source.starts_with("(() => {\n"));
+        assert!(source.contains("  return (value, slots) => {\n"));
+        assert!(source.contains("    if (field(value, \"type\") === \"pay\") return 0;\n"));
+        assert!(
+            source.contains(
+                "    if (callSlot(slots, 0, value)) return 1;\n    return -1;\n  };\n})()"
+            )
+        );
+    }
+
+    #[test]
+    fn test_dispatch_becomes_switch() {
+        let program = MatchProgram {
+            branches: vec![
+                tag_branch("pay"),
+                tag_branch("refund"),
+                branch(PredicateAst::Wildcard),
+            ],
+            input_schema: None,
+        };
+        let source = generate_js(&compile_program_with_options(
+            &program,
+            &CompileOptions {
+                dispatch: true,
+                ..CompileOptions::default()
+            },
+        ));
+
+        assert!(source.contains("    switch (field(value, \"type\")) {\n      case \"pay\":\n"));
+        assert!(
+            source.contains(
+                "      default:\n        if (true) return 2;\n        return -1;\n    }\n"
+            )
+        );
+    }
+
+    // Runs the generated matcher under node and compares it with `evaluate_plan`.
+    // Slot 0 accepts exactly 42.
+    fn assert_runs_like_evaluator(plan: &CompilePlan, inputs: &[Value]) {
+        let script = format!(
+            "const matcher = {};\nconst slots = [(item) => item === 42];\nconst inputs = {};\nprocess.stdout.write(JSON.stringify([...inputs, undefined].map((input) => matcher(input, slots))));\n",
+            generate_js(plan),
+            Value::Array(inputs.to_vec())
+        );
+        // CI installs node; a missing binary fails rather than skipping the check.
+        let output = Command::new("node")
+            .arg("-e")
+            .arg(&script)
+            .output()
+            .unwrap_or_else(|err| panic!("failed to run node: {}", err));
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let actual: Vec<i64> = serde_json::from_slice(&output.stdout).unwrap();
+
+        let expected: Vec<i64> = inputs
+            .iter()
+            .map(Some)
+            .chain([None])
+            .map(|input| {
+                evaluate_plan(plan, input, |slot, value| {
+                    slot == 0 && value == Some(&json!(42))
+                })
+                .map_or(-1, |action| action as i64)
+            })
+            .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_generated_source_matches_evaluator() {
+        let program = parse_program_text(
+            r#"
+            { type: "pay", amount: > 0 } => 0
+            { type: "refund", reason: startsWith("dup") } => 1
+            { type: "refund" } => 2
+            exact { id: oneOf(1, 2, 3) } => 3
+            [string, ...number] => 4
+            arrayOf({ ok: true }) => 5
+            regex("^ab+c$", "gi") => 6
+            !null & slot(0) => 7
+            null => 8
+            "#,
+        )
+        .unwrap();
+        let inputs = [
+            json!({ "type": "pay", "amount": 5 }),
+            json!({ "type": "pay", "amount": 0 }),
+            json!({ "type": "refund", "reason": "duplicate" }),
+            json!({ "type": "refund" }),
+            json!({ "id": 2.0 }),
+            json!({ "id": 2, "extra": true }),
+            json!(["a", 1, 2]),
+            json!(["a", "b"]),
+            json!([{ "ok": true }, { "ok": true, "more": 1 }]),
+            json!([]),
+            json!("ABBC"),
+            json!("abbc"),
+            json!(42),
+            json!(null),
+            json!({ "length": 0 }),
+        ];
+        assert_runs_like_evaluator(
+            &compile_program_with_options(&program, &CompileOptions::default()),
+            &inputs,
+        );
+
+        let router = parse_program_text(
+            r#"
+            { type: "pay" } => 0
+            { type: 1 } & { amount: < 10 } => 1
+            { type: true } => 2
+            _ => 3
+            "#,
+        )
+        .unwrap();
+        let plan = compile_program_with_options(
+            &router,
+            &CompileOptions {
+                dispatch: true,
+                ..CompileOptions::default()
+            },
+        );
+        assert!(plan.dispatch.is_some());
+        assert_runs_like_evaluator(
+            &plan,
+            &[
+                json!({ "type": "pay" }),
+                json!({ "type": 1.0, "amount": 3 }),
+                json!({ "type": 1, "amount": 30 }),
+                json!({ "type": true }),
+                json!({ "type": "refund" }),
+                json!({ "type": "other" }),
+                json!("pay"),
+            ],
+        );
+    }
+}
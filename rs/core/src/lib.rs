@@ -0,0 +1,38 @@
+mod binary;
+mod compiler;
+mod decision;
+mod dsl;
+mod error;
+mod evaluator;
+mod exhaustiveness;
+mod explain;
+mod js_codegen;
+mod migration;
+mod ordering;
+mod rust_codegen;
+mod simplify;
+mod subsumption;
+
+pub use binary::{DecodeError, decode_plan, encode_plan};
+pub use compiler::{
+    Binding, BranchAst, COMPILE_PLAN_VERSION, CompileDiagnostic, CompileError, CompileOptions,
+    CompilePlan, CompiledBranch, CompiledPredicate, MatchProgram, PredicateAst, RelatedLocation,
+    Severity, compile_program, compile_program_with_options, parse_program_json,
+    try_compile_program,
+};
+pub use decision::{
+    DecisionCandidate, DecisionCase, DecisionNode, DecisionTest, DispatchCase, DispatchTable,
+};
+pub use dsl::{
+    DslError, format_predicate, format_program, parse_predicate_text, parse_program_text,
+};
+pub use error::MatchPatternError;
+// For the wasm bindings in `match-pattern-rs`.
+#[doc(hidden)]
+pub use error::from_value;
+pub use evaluator::{PlanMatch, evaluate_plan, evaluate_plan_with_bindings, evaluate_predicate};
+pub use exhaustiveness::InputSchema;
+pub use explain::{BranchTrace, MatchTrace, PredicateFailure, explain_plan};
+pub use js_codegen::generate_js;
+pub use migration::{PlanLoadError, load_plan, upgrade_plan};
+pub use rust_codegen::{InvalidFunctionName, generate_rust};
@@ -0,0 +1,367 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::compiler::{COMPILE_PLAN_VERSION, CompilePlan};
+use crate::decision::DecisionNode;
+use crate::error::from_value;
+
+// Rewrites a plan of version `n` (as JSON) into version `n + 1`.
+type Migration = fn(Value) -> Result<Value, String>;
+
+// `MIGRATIONS[n - 1]` upgrades version `n`. Bumping `COMPILE_PLAN_VERSION` needs a
+// new step here, even if it only changes `version`.
+const MIGRATIONS: [Migration; 1] = [from_version_1];
+
+const _: () = assert!(MIGRATIONS.len() as u32 + 1 == COMPILE_PLAN_VERSION);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanLoadError {
+    MissingVersion,
+    UnsupportedVersion { version: u64 },
+    // Produced by a newer compiler; this build cannot know what changed.
+    NewerVersion { version: u64, supported: u32 },
+    MigrationFailed { from: u32, message: String },
+    Invalid { path: Vec<String>, message: String },
+}
+
+impl fmt::Display for PlanLoadError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanLoadError::MissingVersion => write!(formatter, "plan has no numeric version"),
+            PlanLoadError::UnsupportedVersion { version } => {
+                write!(formatter, "plan version {} is not supported", version)
+            }
+            PlanLoadError::NewerVersion { version, supported } => write!(
+                formatter,
+                "plan version {} is newer than the supported version {}",
+                version, supported
+            ),
+            PlanLoadError::MigrationFailed { from, message } => write!(
+                formatter,
+                "failed to migrate plan from version {}: {}",
+                from, message
+            ),
+            PlanLoadError::Invalid { path, message } if path.is_empty() => {
+                write!(formatter, "invalid plan: {}", message)
+            }
+            PlanLoadError::Invalid { path, message } => {
+                write!(formatter, "invalid plan at {}: {}", path.join("."), message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlanLoadError {}
+
+// Reads a serialized `CompilePlan`, upgrading older versions step by step.
+pub fn load_plan(plan: Value) -> Result<CompilePlan, PlanLoadError> {
+    let plan = migrate(plan, &MIGRATIONS)?;
+    let plan =
+        from_value(plan).map_err(|(path, message)| PlanLoadError::Invalid { path, message })?;
+    check_branch_indices(&plan)?;
+    Ok(plan)
+}
+
+// Re-checks a plan that was deserialized directly, e.g. by `decode_plan`.
+pub fn upgrade_plan(plan: CompilePlan) -> Result<CompilePlan, PlanLoadError> {
+    if plan.version == COMPILE_PLAN_VERSION {
+        check_branch_indices(&plan)?;
+        return Ok(plan);
+    }
+    let value = serde_json::to_value(plan).map_err(|err| PlanLoadError::Invalid {
+        path: Vec::new(),
+        message: err.to_string(),
+    })?;
+    load_plan(value)
+}
+
+// Version 2 added compiled predicate kinds (`oneOf`, `range`, `regex`, `tuple`, ...) and
+// optional plan and diagnostic fields. A version 1 plan uses none of them and reads
+// as is.
+fn from_version_1(plan: Value) -> Result<Value, String> {
+    Ok(plan)
+}
+
+// Evaluation and code generation index `branches` with these, so a hand-edited plan
+// must not point past the end.
+fn check_branch_indices(plan: &CompilePlan) -> Result<(), PlanLoadError> {
+    fn check(index: usize, count: usize, path: &[&str]) -> Result<(), PlanLoadError> {
+        if index < count {
+            return Ok(());
+        }
+        Err(PlanLoadError::Invalid {
+            path: path.iter().map(|segment| segment.to_string()).collect(),
+            message: format!("branch index {} out of range", index),
+        })
+    }
+
+    fn check_node(
+        node: &DecisionNode,
+        count: usize,
+        path: &mut Vec<String>,
+    ) -> Result<(), PlanLoadError> {
+        let mut within = |segments: &[&str], node: &DecisionNode| {
+            let depth = path.len();
+            path.extend(segments.iter().map(|segment| segment.to_string()));
+            let result = check_node(node, count, path);
+            path.truncate(depth);
+            result
+        };
+        match node {
+            DecisionNode::Switch { cases, default, .. } => {
+                for (index, case) in cases.iter().enumerate() {
+                    within(&["cases", &index.to_string(), "node"], &case.node)?;
+                }
+                within(&["default"], default)
+            }
+            DecisionNode::Leaf { candidates } => {
+                for (index, candidate) in candidates.iter().enumerate() {
+                    let index = index.to_string();
+                    let mut segments: Vec<&str> = path.iter().map(String::as_str).collect();
+                    segments.extend(["candidates", &index, "branchIndex"]);
+                    check(candidate.branch_index, count, &segments)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    let count = plan.branches.len();
+    if let Some(tree) = &plan.decision_tree {
+        check_node(tree, count, &mut vec!["decisionTree".to_string()])?;
+    }
+    if let Some(dispatch) = &plan.dispatch {
+        for (case_index, case) in dispatch.cases.iter().enumerate() {
+            for (index, branch_index) in case.branches.iter().enumerate() {
+                let path = [
+                    "dispatch",
+                    "cases",
+                    &case_index.to_string(),
+                    "branches",
+                    &index.to_string(),
+                ];
+                check(*branch_index, count, &path)?;
+            }
+        }
+        for (index, branch_index) in dispatch.fallback.iter().enumerate() {
+            check(
+                *branch_index,
+                count,
+                &["dispatch", "fallback", &index.to_string()],
+            )?;
+        }
+    }
+    for (index, binding) in plan.bindings.iter().enumerate() {
+        check(
+            binding.branch_index,
+            count,
+            &["bindings", &index.to_string(), "branchIndex"],
+        )?;
+    }
+    for (index, diagnostic) in plan.diagnostics.iter().enumerate() {
+        let index = index.to_string();
+        if let Some(branch_index) = diagnostic.branch_index {
+            check(branch_index, count, &["diagnostics", &index, "branchIndex"])?;
+        }
+        if let Some(shadowed_by) = diagnostic.shadowed_by {
+            check(shadowed_by, count, &["diagnostics", &index, "shadowedBy"])?;
+        }
+        for (related_index, related) in diagnostic.related.iter().enumerate() {
+            let path = [
+                "diagnostics",
+                &index,
+                "related",
+                &related_index.to_string(),
+                "branchIndex",
+            ];
+            check(related.branch_index, count, &path)?;
+        }
+    }
+    Ok(())
+}
+
+fn migrate(mut plan: Value, migrations: &[Migration]) -> Result<Value, PlanLoadError> {
+    let supported = migrations.len() as u32 + 1;
+    let version = plan
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or(PlanLoadError::MissingVersion)?;
+    if version == 0 {
+        return Err(PlanLoadError::UnsupportedVersion { version });
+    }
+    if version > u64::from(supported) {
+        return Err(PlanLoadError::NewerVersion { version, supported });
+    }
+
+    for from in version as u32..supported {
+        plan = migrations[from as usize - 1](plan)
+            .map_err(|message| PlanLoadError::MigrationFailed { from, message })?;
+        if let Some(fields) = plan.as_object_mut() {
+            fields.insert("version".to_string(), Value::from(from + 1));
+        }
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{
+        CompiledPredicate, MatchProgram, PredicateAst, compile_program, tests::branch,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn test_loads_current_and_rejects_newer_plans() {
+        let plan = compile_program(&MatchProgram {
+            branches: vec![branch(PredicateAst::IsString)],
+            input_schema: None,
+        });
+        let value = serde_json::to_value(&plan).unwrap();
+        assert_eq!(load_plan(value.clone()), Ok(plan.clone()));
+        assert_eq!(upgrade_plan(plan.clone()), Ok(plan.clone()));
+
+        let mut newer = value.clone();
+        newer["version"] = json!(COMPILE_PLAN_VERSION + 1);
+        assert_eq!(
+            load_plan(newer),
+            Err(PlanLoadError::NewerVersion {
+                version: u64::from(COMPILE_PLAN_VERSION) + 1,
+                supported: COMPILE_PLAN_VERSION,
+            })
+        );
+        let mut missing = value;
+        missing.as_object_mut().unwrap().remove("version");
+        assert_eq!(load_plan(missing), Err(PlanLoadError::MissingVersion));
+        assert_eq!(
+            load_plan(json!({ "version": 0 })),
+            Err(PlanLoadError::UnsupportedVersion { version: 0 })
+        );
+    }
+
+    #[test]
+    fn test_loads_version_1_plan() {
+        let plan = json!({
+            "version": 1,
+            "branches": [
+                {
+                    "actionIndex": 0,
+                    "predicate": { "kind": "tagEq", "key": "type", "value": "pay" }
+                },
+                { "actionIndex": 1, "predicate": { "kind": "true" } },
+                { "actionIndex": 2, "predicate": { "kind": "slot", "slot": 0 } }
+            ],
+            "diagnostics": [
+                {
+                    "code": "unreachable_branch",
+                    "level": "warning",
+                    "message": "branch 2 is shadowed by a previous predicate",
+                    "branchIndex": 2
+                }
+            ],
+            "dynamicSlotCount": 1
+        });
+
+        let loaded = load_plan(plan).unwrap();
+        assert_eq!(loaded.version, COMPILE_PLAN_VERSION);
+        assert_eq!(loaded.branches.len(), 3);
+        assert_eq!(
+            loaded.branches[2].predicate,
+            CompiledPredicate::Slot { slot: 0 }
+        );
+        assert_eq!(loaded.diagnostics[0].branch_index, Some(2));
+        assert!(loaded.decision_tree.is_none() && loaded.bindings.is_empty());
+    }
+
+    #[test]
+    fn test_migrations_run_in_order() {
+        fn rename_kind(mut plan: Value) -> Result<Value, String> {
+            plan["branches"][0]["kind"] = json!("renamed");
+            Ok(plan)
+        }
+        fn require_kind(plan: Value) -> Result<Value, String> {
+            if plan["branches"][0]["kind"] == json!("renamed") {
+                Ok(plan)
+            } else {
+                Err("branch 0 has no kind".to_string())
+            }
+        }
+        let migrations: [Migration; 2] = [rename_kind, require_kind];
+
+        let plan = json!({ "version": 1, "branches": [{}] });
+        assert_eq!(
+            migrate(plan, &migrations),
+            Ok(json!({ "version": 3, "branches": [{ "kind": "renamed" }] }))
+        );
+
+        let plan = json!({ "version": 2, "branches": [{}] });
+        assert_eq!(
+            migrate(plan, &migrations),
+            Err(PlanLoadError::MigrationFailed {
+                from: 2,
+                message: "branch 0 has no kind".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_branch_indices() {
+        let plan = |extra: Value| {
+            let mut plan = json!({
+                "version": COMPILE_PLAN_VERSION,
+                "branches": [{ "actionIndex": 0, "predicate": { "kind": "true" } }],
+                "diagnostics": [],
+                "dynamicSlotCount": 0
+            });
+            for (key, value) in extra.as_object().unwrap() {
+                plan[key] = value.clone();
+            }
+            plan
+        };
+        let invalid = |path: &[&str]| PlanLoadError::Invalid {
+            path: path.iter().map(|segment| segment.to_string()).collect(),
+            message: "branch index 5 out of range".to_string(),
+        };
+
+        let dispatch = plan(json!({ "dispatch": { "key": "type", "cases": [], "fallback": [5] } }));
+        assert_eq!(
+            load_plan(dispatch),
+            Err(invalid(&["dispatch", "fallback", "0"]))
+        );
+        let tree = plan(json!({
+            "decisionTree": {
+                "kind": "switch",
+                "test": { "kind": "isNull" },
+                "cases": [],
+                "default": {
+                    "kind": "leaf",
+                    "candidates": [
+                        { "branchIndex": 0, "predicate": { "kind": "true" } },
+                        { "branchIndex": 5, "predicate": { "kind": "true" } }
+                    ]
+                }
+            }
+        }));
+        assert_eq!(
+            load_plan(tree),
+            Err(invalid(&[
+                "decisionTree",
+                "default",
+                "candidates",
+                "1",
+                "branchIndex"
+            ]))
+        );
+        let bindings = plan(json!({ "bindings": [{ "branchIndex": 5, "name": "x", "path": [] }] }));
+        let loaded = from_value::<CompilePlan>(bindings.clone()).unwrap();
+        assert_eq!(
+            load_plan(bindings),
+            Err(invalid(&["bindings", "0", "branchIndex"]))
+        );
+        assert_eq!(
+            upgrade_plan(loaded),
+            Err(invalid(&["bindings", "0", "branchIndex"]))
+        );
+    }
+}
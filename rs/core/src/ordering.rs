@@ -0,0 +1,162 @@
+use serde_json::Value;
+
+use crate::compiler::{CompiledPredicate, contains_slot};
+
+// A slot calls back into user code, so it outweighs any structural check.
+const SLOT_COST: u32 = 100;
+const REGEX_COST: u32 = 20;
+// Element predicates run once per array element; assume a few elements.
+const ELEMENT_FACTOR: u32 = 4;
+
+// Rough relative cost of evaluating `predicate` once.
+pub(crate) fn predicate_cost(predicate: &CompiledPredicate) -> u32 {
+    match predicate {
+        CompiledPredicate::True
+        | CompiledPredicate::TypeOf { .. }
+        | CompiledPredicate::IsNull
+        | CompiledPredicate::IsUndefined
+        | CompiledPredicate::Range { .. }
+        | CompiledPredicate::ArrayLength { .. } => 1,
+        CompiledPredicate::Eq { value } => value_cost(value),
+        CompiledPredicate::TagEq { value, .. } => value_cost(value).saturating_add(1),
+        CompiledPredicate::OneOf { values } => total(values.iter().map(value_cost)).max(1),
+        CompiledPredicate::StartsWith { .. }
+        | CompiledPredicate::EndsWith { .. }
+        | CompiledPredicate::Contains { .. } => 3,
+        CompiledPredicate::Regex { .. } => REGEX_COST,
+        CompiledPredicate::Shape { fields, .. } => {
+            total(fields.values().map(predicate_cost)).saturating_add(1)
+        }
+        CompiledPredicate::Tuple { items, rest } => {
+            let rest = rest.as_deref().map_or(0, element_cost);
+            total(items.iter().map(predicate_cost))
+                .saturating_add(rest)
+                .saturating_add(1)
+        }
+        CompiledPredicate::ArrayOf { predicate } => element_cost(predicate).saturating_add(1),
+        CompiledPredicate::And { predicates } | CompiledPredicate::Or { predicates } => {
+            total(predicates.iter().map(predicate_cost))
+        }
+        CompiledPredicate::Not { predicate } => predicate_cost(predicate),
+        CompiledPredicate::Slot { .. } => SLOT_COST,
+    }
+}
+
+// Deep equality walks the whole expected value.
+fn value_cost(value: &Value) -> u32 {
+    match value {
+        Value::Array(items) => total(items.iter().map(value_cost)).saturating_add(1),
+        Value::Object(map) => total(map.values().map(value_cost)).saturating_add(1),
+        _ => 1,
+    }
+}
+
+// Costs saturate, so deeply nested element predicates just rank as very expensive.
+fn total(costs: impl Iterator<Item = u32>) -> u32 {
+    costs.fold(0, u32::saturating_add)
+}
+
+fn element_cost(predicate: &CompiledPredicate) -> u32 {
+    predicate_cost(predicate).saturating_mul(ELEMENT_FACTOR)
+}
+
+// Stable-sorts the operands of every `and` / `or` so cheaper ones run first.
+// Operands containing a slot go last and keep their relative order, so slots are
+// called in the written order, though a failing cheap check may now skip them.
+pub(crate) fn order_by_cost(predicate: &CompiledPredicate) -> CompiledPredicate {
+    match predicate {
+        CompiledPredicate::And { predicates } => CompiledPredicate::And {
+            predicates: order_operands(predicates),
+        },
+        CompiledPredicate::Or { predicates } => CompiledPredicate::Or {
+            predicates: order_operands(predicates),
+        },
+        CompiledPredicate::Not { predicate } => CompiledPredicate::Not {
+            predicate: Box::new(order_by_cost(predicate)),
+        },
+        CompiledPredicate::Shape { fields, exact } => CompiledPredicate::Shape {
+            fields: fields
+                .iter()
+                .map(|(key, node)| (key.clone(), order_by_cost(node)))
+                .collect(),
+            exact: *exact,
+        },
+        CompiledPredicate::Tuple { items, rest } => CompiledPredicate::Tuple {
+            items: items.iter().map(order_by_cost).collect(),
+            rest: rest.as_deref().map(|node| Box::new(order_by_cost(node))),
+        },
+        CompiledPredicate::ArrayOf { predicate } => CompiledPredicate::ArrayOf {
+            predicate: Box::new(order_by_cost(predicate)),
+        },
+        other => other.clone(),
+    }
+}
+
+fn order_operands(predicates: &[CompiledPredicate]) -> Vec<CompiledPredicate> {
+    let mut ordered: Vec<CompiledPredicate> = predicates.iter().map(order_by_cost).collect();
+    ordered.sort_by_key(|node| {
+        if contains_slot(node) {
+            (1, 0)
+        } else {
+            (0, predicate_cost(node))
+        }
+    });
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cheap_conjuncts_first_and_slots_in_order() {
+        let deep_eq = CompiledPredicate::Eq {
+            value: json!({ "type": "pay", "lines": [1, 2, 3] }),
+        };
+        let tag = CompiledPredicate::TagEq {
+            key: "type".to_string(),
+            value: json!("pay"),
+        };
+        let ordered = order_by_cost(&CompiledPredicate::And {
+            predicates: vec![
+                CompiledPredicate::Slot { slot: 1 },
+                deep_eq.clone(),
+                CompiledPredicate::Slot { slot: 0 },
+                tag.clone(),
+            ],
+        });
+
+        assert_eq!(
+            ordered,
+            CompiledPredicate::And {
+                predicates: vec![
+                    tag,
+                    deep_eq,
+                    CompiledPredicate::Slot { slot: 1 },
+                    CompiledPredicate::Slot { slot: 0 },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_deeply_nested_array_of_saturates() {
+        let nested = (0..20).fold(CompiledPredicate::IsNull, |predicate, _| {
+            CompiledPredicate::ArrayOf {
+                predicate: Box::new(predicate),
+            }
+        });
+        assert_eq!(predicate_cost(&nested), u32::MAX);
+
+        let ordered = order_by_cost(&CompiledPredicate::And {
+            predicates: vec![nested.clone(), nested.clone(), CompiledPredicate::IsNull],
+        });
+        assert_eq!(
+            ordered,
+            CompiledPredicate::And {
+                predicates: vec![CompiledPredicate::IsNull, nested.clone(), nested],
+            }
+        );
+    }
+}
@@ -0,0 +1,647 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::compiler::{CompilePlan, CompiledBranch, CompiledPredicate};
+use crate::evaluator::compile_regex;
+
+const FIELD_HELPER: &str = r#"    // Arrays behave like JS objects keyed by their indices.
+    fn field<'a>(value: Option<&'a Value>, key: &str) -> Option<&'a Value> {
+        match value? {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => {
+                let is_canonical = key == "0" || !key.starts_with('0');
+                if !is_canonical || key.is_empty() || !key.chars().all(|ch| ch.is_ascii_digit()) {
+                    return None;
+                }
+                key.parse::<usize>().ok().and_then(|index| items.get(index))
+            }
+            _ => None,
+        }
+    }
+"#;
+
+const KEY_COUNT_HELPER: &str = r#"    fn key_count(value: Option<&Value>) -> Option<usize> {
+        match value? {
+            Value::Object(map) => Some(map.len()),
+            Value::Array(items) => Some(items.len()),
+            _ => None,
+        }
+    }
+"#;
+
+const VALUES_EQUAL_HELPER: &str = r#"    // Numbers compare by value, so `1` equals `1.0`.
+    fn values_equal(left: &Value, right: &Value) -> bool {
+        match (left, right) {
+            (Value::Number(left), Value::Number(right)) => left.as_f64() == right.as_f64(),
+            (Value::Array(left), Value::Array(right)) => {
+                left.len() == right.len()
+                    && left.iter().zip(right).all(|(left, right)| values_equal(left, right))
+            }
+            (Value::Object(left), Value::Object(right)) => {
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .all(|(key, left)| right.get(key).is_some_and(|right| values_equal(left, right)))
+            }
+            _ => left == right,
+        }
+    }
+"#;
+
+const KEYWORDS: &[&str] = &[
+    "Self", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if",
+    "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidFunctionName {
+    pub name: String,
+}
+
+impl fmt::Display for InvalidFunctionName {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "`{}` is not a valid Rust function name",
+            self.name
+        )
+    }
+}
+
+impl std::error::Error for InvalidFunctionName {}
+
+// Emits a Rust function with the same contract as `evaluate_plan`:
+//
+//     pub fn name<S>(value: Option<&Value>, mut slots: S) -> Option<usize>
+//     where S: FnMut(u32, Option<&Value>) -> bool
+//
+// The output depends on `serde_json`, and on `regex` when the plan has regex tests.
+// A `dispatch` table with string tags becomes a `match`; `decision_tree` is ignored.
+// `function_name` must be a plain ASCII identifier that is not a keyword.
+pub fn generate_rust(
+    plan: &CompilePlan,
+    function_name: &str,
+) -> Result<String, InvalidFunctionName> {
+    if !is_identifier(function_name) {
+        return Err(InvalidFunctionName {
+            name: function_name.to_string(),
+        });
+    }
+
+    let mut generator = RustGenerator::default();
+    let body = match &plan.dispatch {
+        Some(dispatch) if dispatch.cases.iter().all(|case| case.value.is_string()) => {
+            generator.uses_field = true;
+            let mut body = format!(
+                "    match field(value, {:?}).and_then(Value::as_str) {{\n",
+                dispatch.key
+            );
+            for case in &dispatch.cases {
+                let tag = case.value.as_str().unwrap_or_default();
+                body.push_str(&format!("        Some({:?}) => {{\n", tag));
+                body.push_str(&generator.branch_checks(
+                    &plan.branches,
+                    &case.branches,
+                    "            ",
+                ));
+                body.push_str("            None\n        }\n");
+            }
+            body.push_str("        _ => {\n");
+            body.push_str(&generator.branch_checks(
+                &plan.branches,
+                &dispatch.fallback,
+                "            ",
+            ));
+            body.push_str("            None\n        }\n    }\n");
+            body
+        }
+        _ => {
+            let all: Vec<usize> = (0..plan.branches.len()).collect();
+            let mut body = generator.branch_checks(&plan.branches, &all, "    ");
+            body.push_str("    None\n");
+            body
+        }
+    };
+
+    let mut helpers = String::new();
+    if generator.uses_field {
+        helpers.push_str(FIELD_HELPER);
+    }
+    if generator.uses_key_count {
+        helpers.push_str(KEY_COUNT_HELPER);
+    }
+    if !generator.constants.is_empty() {
+        helpers.push_str(VALUES_EQUAL_HELPER);
+    }
+    for (index, constant) in generator.constants.iter().enumerate() {
+        helpers.push_str(&format!(
+            "    static CONSTANT_{}: LazyLock<Value> =\n        LazyLock::new(|| serde_json::from_str({:?}).unwrap());\n",
+            index,
+            constant.to_string()
+        ));
+    }
+    for (index, pattern) in generator.regexes.iter().enumerate() {
+        helpers.push_str(&format!(
+            "    static REGEX_{}: LazyLock<regex::Regex> =\n        LazyLock::new(|| regex::Regex::new({:?}).unwrap());\n",
+            index, pattern
+        ));
+    }
+    let lazy = if generator.constants.is_empty() && generator.regexes.is_empty() {
+        ""
+    } else {
+        "    use std::sync::LazyLock;\n"
+    };
+
+    Ok(format!(
+        "#[allow(unused_mut, unused_parens, unused_variables, clippy::all)]\npub fn {}<S>(value: Option<&serde_json::Value>, mut slots: S) -> Option<usize>\nwhere\n    S: FnMut(u32, Option<&serde_json::Value>) -> bool,\n{{\n    use serde_json::Value;\n{}{}\n{}}}\n",
+        function_name, lazy, helpers, body
+    ))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first == '_' || first.is_ascii_alphabetic())
+        && chars.all(|ch| ch == '_' || ch.is_ascii_alphanumeric())
+        && name != "_"
+        && !KEYWORDS.contains(&name)
+}
+
+#[derive(Debug, Default)]
+struct RustGenerator {
+    constants: Vec<Value>,
+    regexes: Vec<String>,
+    uses_field: bool,
+    uses_key_count: bool,
+    depth: usize,
+}
+
+impl RustGenerator {
+    fn branch_checks(
+        &mut self,
+        branches: &[CompiledBranch],
+        indices: &[usize],
+        indent: &str,
+    ) -> String {
+        indices
+            .iter()
+            .map(|index| {
+                let branch = &branches[*index];
+                format!(
+                    "{}if {} {{\n{}    return Some({});\n{}}}\n",
+                    indent,
+                    self.predicate(&branch.predicate, "value"),
+                    indent,
+                    branch.action_index,
+                    indent
+                )
+            })
+            .collect()
+    }
+
+    // A Rust `bool` expression testing `target`, an expression of type `Option<&Value>`.
+    fn predicate(&mut self, predicate: &CompiledPredicate, target: &str) -> String {
+        match predicate {
+            CompiledPredicate::TypeOf { value } => match value.as_str() {
+                "number" => format!("matches!({}, Some(Value::Number(_)))", target),
+                "string" => format!("matches!({}, Some(Value::String(_)))", target),
+                "boolean" => format!("matches!({}, Some(Value::Bool(_)))", target),
+                "object" => format!(
+                    "matches!({}, Some(Value::Object(_) | Value::Array(_)))",
+                    target
+                ),
+                _ => "false".to_string(),
+            },
+            CompiledPredicate::IsNull => format!("matches!({}, Some(Value::Null))", target),
+            CompiledPredicate::IsUndefined => format!("{}.is_none()", target),
+            CompiledPredicate::Eq { value } => self.equals(target, value),
+            CompiledPredicate::TagEq { key, value } => {
+                self.uses_field = true;
+                self.equals(&format!("field({}, {:?})", target, key), value)
+            }
+            CompiledPredicate::OneOf { values } => {
+                let parts: Vec<String> = values
+                    .iter()
+                    .map(|value| self.equals(target, value))
+                    .collect();
+                if parts.is_empty() {
+                    "false".to_string()
+                } else {
+                    format!("({})", parts.join(" || "))
+                }
+            }
+            CompiledPredicate::StartsWith { value } => {
+                string_method(target, &format!("text.starts_with({:?})", value))
+            }
+            CompiledPredicate::EndsWith { value } => {
+                string_method(target, &format!("text.ends_with({:?})", value))
+            }
+            CompiledPredicate::Contains { value } => {
+                string_method(target, &format!("text.contains({:?})", value))
+            }
+            CompiledPredicate::Regex { pattern, flags } => {
+                // Patterns the native evaluator rejects never match there either.
+                let Ok(regex) = compile_regex(pattern, flags.as_deref()) else {
+                    return "false".to_string();
+                };
+                let index = self.regexes.len();
+                self.regexes.push(regex.as_str().to_string());
+                string_method(target, &format!("REGEX_{}.is_match(text)", index))
+            }
+            CompiledPredicate::Range {
+                min,
+                max,
+                min_inclusive,
+                max_inclusive,
+            } => {
+                let mut parts = Vec::new();
+                if let Some(min) = min {
+                    let operator = if *min_inclusive { ">=" } else { ">" };
+                    parts.push(format!("number {} {}", operator, rust_f64(*min)));
+                }
+                if let Some(max) = max {
+                    let operator = if *max_inclusive { "<=" } else { "<" };
+                    parts.push(format!("number {} {}", operator, rust_f64(*max)));
+                }
+                if parts.is_empty() {
+                    parts.push("true".to_string());
+                }
+                format!(
+                    "{}.and_then(Value::as_f64).is_some_and(|number| {})",
+                    target,
+                    parts.join(" && ")
+                )
+            }
+            CompiledPredicate::Shape { fields, exact } => {
+                let mut parts = vec![format!(
+                    "matches!({}, Some(Value::Object(_) | Value::Array(_)))",
+                    target
+                )];
+                for (key, node) in fields {
+                    self.uses_field = true;
+                    let child = format!("field({}, {:?})", target, key);
+                    parts.push(self.predicate(node, &child));
+                }
+                if *exact {
+                    self.uses_key_count = true;
+                    parts.push(format!("key_count({}) == Some({})", target, fields.len()));
+                }
+                format!("({})", parts.join(" && "))
+            }
+            CompiledPredicate::Tuple { items, rest } => {
+                let array = format!("items{}", self.depth);
+                let length = match rest {
+                    Some(_) => format!("{}.len() >= {}", array, items.len()),
+                    None => format!("{}.len() == {}", array, items.len()),
+                };
+                let mut parts = vec![format!(
+                    "matches!({}, Some(Value::Array({})) if {})",
+                    target, array, length
+                )];
+                for (index, node) in items.iter().enumerate() {
+                    self.uses_field = true;
+                    let child = format!("field({}, \"{}\")", target, index);
+                    parts.push(self.predicate(node, &child));
+                }
+                if let Some(rest) = rest {
+                    let element = self.element(rest);
+                    parts.push(format!(
+                        "{}.and_then(Value::as_array).is_some_and(|{}| {}[{}..].iter().all({}))",
+                        target,
+                        array,
+                        array,
+                        items.len(),
+                        element
+                    ));
+                }
+                format!("({})", parts.join(" && "))
+            }
+            CompiledPredicate::ArrayOf { predicate } => {
+                let array = format!("items{}", self.depth);
+                let element = self.element(predicate);
+                format!(
+                    "{}.and_then(Value::as_array).is_some_and(|{}| {}.iter().all({}))",
+                    target, array, array, element
+                )
+            }
+            CompiledPredicate::ArrayLength { length } => format!(
+                "{}.and_then(Value::as_array).is_some_and(|items| items.len() == {})",
+                target, length
+            ),
+            CompiledPredicate::And { predicates } => {
+                self.junction(predicates, target, " && ", "true")
+            }
+            CompiledPredicate::Or { predicates } => {
+                self.junction(predicates, target, " || ", "false")
+            }
+            CompiledPredicate::Not { predicate } => {
+                let inner = self.predicate(predicate, target);
+                format!("!({})", inner)
+            }
+            CompiledPredicate::Slot { slot } => format!("slots({}, {})", slot, target),
+            CompiledPredicate::True => "true".to_string(),
+        }
+    }
+
+    fn junction(
+        &mut self,
+        predicates: &[CompiledPredicate],
+        target: &str,
+        operator: &str,
+        empty: &str,
+    ) -> String {
+        if predicates.is_empty() {
+            return empty.to_string();
+        }
+        let parts: Vec<String> = predicates
+            .iter()
+            .map(|node| self.predicate(node, target))
+            .collect();
+        format!("({})", parts.join(operator))
+    }
+
+    // A closure testing one array element.
+    fn element(&mut self, predicate: &CompiledPredicate) -> String {
+        let name = format!("element{}", self.depth);
+        self.depth += 1;
+        let body = self.predicate(predicate, &format!("Some({})", name));
+        self.depth -= 1;
+        format!("|{}| {}", name, body)
+    }
+
+    fn equals(&mut self, target: &str, value: &Value) -> String {
+        match value {
+            Value::Null => format!("matches!({}, Some(Value::Null))", target),
+            Value::Bool(flag) => format!("{}.and_then(Value::as_bool) == Some({})", target, flag),
+            Value::Number(number) => match number.as_f64() {
+                Some(number) => format!(
+                    "{}.and_then(Value::as_f64) == Some({})",
+                    target,
+                    rust_f64(number)
+                ),
+                None => "false".to_string(),
+            },
+            Value::String(text) => {
+                format!("{}.and_then(Value::as_str) == Some({:?})", target, text)
+            }
+            Value::Array(_) | Value::Object(_) => {
+                let index = self.constants.len();
+                self.constants.push(value.clone());
+                format!(
+                    "{}.is_some_and(|item| values_equal(item, &CONSTANT_{}))",
+                    target, index
+                )
+            }
+        }
+    }
+}
+
+fn string_method(target: &str, test: &str) -> String {
+    format!(
+        "{}.and_then(Value::as_str).is_some_and(|text| {})",
+        target, test
+    )
+}
+
+fn rust_f64(number: f64) -> String {
+    if number.is_nan() {
+        "f64::NAN".to_string()
+    } else if number.is_infinite() {
+        if number > 0.0 {
+            "f64::INFINITY".to_string()
+        } else {
+            "f64::NEG_INFINITY".to_string()
+        }
+    } else {
+        format!("{:?}_f64", number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::{
+        BranchAst, CompileOptions, MatchProgram, PredicateAst, compile_program,
+        compile_program_with_options, tests::branch,
+    };
+    use crate::dsl::parse_program_text;
+    use crate::evaluator::evaluate_plan;
+    use serde_json::json;
+
+    const FIXTURE: &str = "tests/fixtures/rust_codegen.rs";
+
+    // Checked in as `tests/fixtures/rust_codegen.rs`, so the generated code is compiled
+    // with the tests. Rewrite it with `UPDATE_FIXTURES=1 cargo test`.
+    mod generated {
+        include!("../tests/fixtures/rust_codegen.rs");
+    }
+
+    fn linear_plan() -> CompilePlan {
+        let program = parse_program_text(
+            r#"
+            { type: "pay", amount: > 0 } => 0
+            { type: "refund", reason: startsWith("dup") } => 1
+            exact { id: oneOf(1, 2, 3) } => 2
+            [string, ...number] => 3
+            arrayOf({ ok: true }) => 4
+            regex("^ab+c$", "i") => 5
+            eq({ a: [1, 2] }) => 6
+            !null & slot(0) => 7
+            null => 8
+            "#,
+        )
+        .unwrap();
+        compile_program(&program)
+    }
+
+    // Tags that need escaping in a Rust string literal.
+    fn dispatch_plan() -> CompilePlan {
+        let tagged = |value: &str, rest: Option<PredicateAst>| {
+            branch(PredicateAst::And {
+                predicates: [PredicateAst::Tag {
+                    key: "type".to_string(),
+                    value: json!(value),
+                }]
+                .into_iter()
+                .chain(rest)
+                .collect(),
+            })
+        };
+        let program = MatchProgram {
+            branches: vec![
+                tagged("pay\u{1b}", None),
+                tagged(
+                    "r\u{e9}\"fund\u{2028}",
+                    Some(PredicateAst::Shape {
+                        fields: [(
+                            "amount".to_string(),
+                            PredicateAst::Range {
+                                min: None,
+                                max: Some(10.0),
+                                min_inclusive: false,
+                                max_inclusive: false,
+                            },
+                        )]
+                        .into(),
+                        exact: false,
+                    }),
+                ),
+                tagged("\\{}", None),
+                branch(PredicateAst::Wildcard),
+            ],
+            input_schema: None,
+        };
+        let plan = compile_program_with_options(
+            &program,
+            &CompileOptions {
+                dispatch: true,
+                ..CompileOptions::default()
+            },
+        );
+        assert!(plan.dispatch.is_some());
+        plan
+    }
+
+    fn fixture_source() -> String {
+        format!(
+            "{}\n{}",
+            generate_rust(&linear_plan(), "route").unwrap(),
+            generate_rust(&dispatch_plan(), "dispatch").unwrap()
+        )
+    }
+
+    #[test]
+    fn test_generates_function_with_slot_callback() {
+        let program = MatchProgram {
+            branches: vec![
+                branch(PredicateAst::Tag {
+                    key: "type".to_string(),
+                    value: json!("pay"),
+                }),
+                BranchAst {
+                    action_index: Some(7),
+                    predicate: PredicateAst::Slot { slot: 0 },
+                    allow: Vec::new(),
+                },
+            ],
+            input_schema: None,
+        };
+        let source = generate_rust(&compile_program(&program), "route_event").unwrap();
+
+        assert!(source.contains("pub fn route_event<S>(value: Option<&serde_json::Value>, mut slots: S) -> Option<usize>\n"));
+        assert!(source.contains(
+            "    if field(value, \"type\").and_then(Value::as_str) == Some(\"pay\") {\n        return Some(0);\n    }\n"
+        ));
+        assert!(
+            source.contains(
+                "    if slots(0, value) {\n        return Some(7);\n    }\n    None\n}\n"
+            )
+        );
+        assert!(!source.contains("LazyLock"));
+    }
+
+    #[test]
+    fn test_object_constants_are_built_once() {
+        let program = MatchProgram {
+            branches: vec![branch(PredicateAst::Eq {
+                value: json!({ "a": [1, 2] }),
+            })],
+            input_schema: None,
+        };
+        let source = generate_rust(&compile_program(&program), "exact").unwrap();
+
+        assert!(source.contains("static CONSTANT_0: LazyLock<Value>"));
+        assert!(source.contains("values_equal(item, &CONSTANT_0)"));
+    }
+
+    #[test]
+    fn test_fixture_is_up_to_date() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURE);
+        if std::env::var_os("UPDATE_FIXTURES").is_some() {
+            std::fs::write(&path, fixture_source()).unwrap();
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), fixture_source());
+    }
+
+    // Slot 0 accepts exactly 42.
+    fn slots(slot: u32, value: Option<&Value>) -> bool {
+        slot == 0 && value == Some(&json!(42))
+    }
+
+    fn assert_agrees(
+        plan: &CompilePlan,
+        generated: impl Fn(Option<&Value>) -> Option<usize>,
+        inputs: &[Value],
+    ) {
+        for input in inputs.iter().map(Some).chain([None]) {
+            assert_eq!(
+                generated(input),
+                evaluate_plan(plan, input, slots),
+                "input {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_generated_code_matches_evaluator() {
+        assert_agrees(
+            &linear_plan(),
+            |input| generated::route(input, slots),
+            &[
+                json!({ "type": "pay", "amount": 5 }),
+                json!({ "type": "pay", "amount": 0 }),
+                json!({ "type": "refund", "reason": "duplicate" }),
+                json!({ "id": 2.0 }),
+                json!({ "id": 2, "extra": true }),
+                json!(["a", 1, 2]),
+                json!(["a", "b"]),
+                json!([{ "ok": true }, { "ok": true, "more": 1 }]),
+                json!("ABBC"),
+                json!({ "a": [1.0, 2] }),
+                json!(42),
+                json!(null),
+            ],
+        );
+        assert_agrees(
+            &dispatch_plan(),
+            |input| generated::dispatch(input, slots),
+            &[
+                json!({ "type": "pay\u{1b}" }),
+                json!({ "type": "r\u{e9}\"fund\u{2028}", "amount": 3 }),
+                json!({ "type": "r\u{e9}\"fund\u{2028}", "amount": 30 }),
+                json!({ "type": "\\{}" }),
+                json!({ "type": "pay" }),
+                json!("pay\u{1b}"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_function_names() {
+        let plan = linear_plan();
+        for name in [
+            "",
+            "_",
+            "1st",
+            "fn",
+            "match",
+            "route-event",
+            "r#type",
+            "naïve",
+        ] {
+            assert_eq!(
+                generate_rust(&plan, name),
+                Err(InvalidFunctionName {
+                    name: name.to_string()
+                })
+            );
+        }
+        assert!(generate_rust(&plan, "_route_2").is_ok());
+    }
+}
@@ -0,0 +1,196 @@
+use crate::compiler::{CompiledPredicate, contains_slot};
+
+// Rewrites a lowered predicate into an equivalent, smaller one. `false` is spelled
+// `Not(True)`. Slot calls keep their order, and a predicate containing a slot is
+// never dropped unless evaluation could not have reached it anyway.
+pub(crate) fn simplify_predicate(predicate: &CompiledPredicate) -> CompiledPredicate {
+    match predicate {
+        CompiledPredicate::Not { predicate } => negate(simplify_predicate(predicate)),
+        CompiledPredicate::And { predicates } => {
+            junction(predicates.iter().map(simplify_predicate).collect(), true)
+        }
+        CompiledPredicate::Or { predicates } => {
+            junction(predicates.iter().map(simplify_predicate).collect(), false)
+        }
+        CompiledPredicate::Shape { fields, exact } => CompiledPredicate::Shape {
+            fields: fields
+                .iter()
+                .map(|(key, node)| (key.clone(), simplify_predicate(node)))
+                .collect(),
+            exact: *exact,
+        },
+        CompiledPredicate::Tuple { items, rest } => CompiledPredicate::Tuple {
+            items: items.iter().map(simplify_predicate).collect(),
+            rest: rest
+                .as_deref()
+                .map(|node| Box::new(simplify_predicate(node))),
+        },
+        CompiledPredicate::ArrayOf { predicate } => CompiledPredicate::ArrayOf {
+            predicate: Box::new(simplify_predicate(predicate)),
+        },
+        other => other.clone(),
+    }
+}
+
+fn always_false() -> CompiledPredicate {
+    CompiledPredicate::Not {
+        predicate: Box::new(CompiledPredicate::True),
+    }
+}
+
+// Pushes a negation through `and` / `or` (De Morgan); `!a || !b` evaluates its
+// operands in the same order as `!(a && b)`, so slot calls are unchanged.
+fn negate(predicate: CompiledPredicate) -> CompiledPredicate {
+    match predicate {
+        CompiledPredicate::Not { predicate } => *predicate,
+        CompiledPredicate::And { predicates } => {
+            junction(predicates.into_iter().map(negate).collect(), false)
+        }
+        CompiledPredicate::Or { predicates } => {
+            junction(predicates.into_iter().map(negate).collect(), true)
+        }
+        other => CompiledPredicate::Not {
+            predicate: Box::new(other),
+        },
+    }
+}
+
+// Builds an `and` (`conjoin`) or `or` from already simplified operands.
+fn junction(operands: Vec<CompiledPredicate>, conjoin: bool) -> CompiledPredicate {
+    let (identity, absorbing) = if conjoin {
+        (CompiledPredicate::True, always_false())
+    } else {
+        (always_false(), CompiledPredicate::True)
+    };
+
+    let flattened = operands.into_iter().flat_map(|operand| match operand {
+        CompiledPredicate::And { predicates } if conjoin => predicates,
+        CompiledPredicate::Or { predicates } if !conjoin => predicates,
+        other => vec![other],
+    });
+
+    let mut items: Vec<CompiledPredicate> = Vec::new();
+    for item in flattened {
+        if item == identity {
+            continue;
+        }
+        if item == absorbing {
+            // Short-circuiting stops here, but slots before this point still run.
+            if !items.iter().any(contains_slot) {
+                return absorbing;
+            }
+            items.push(item);
+            break;
+        }
+        if !contains_slot(&item) && items.contains(&item) {
+            continue;
+        }
+        items.push(item);
+    }
+
+    // Absorption: `a && (a || b)` is `a`, and `a || (a && b)` is `a`.
+    let absorbed: Vec<bool> = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let inner = match item {
+                CompiledPredicate::Or { predicates } if conjoin => predicates,
+                CompiledPredicate::And { predicates } if !conjoin => predicates,
+                _ => return false,
+            };
+            !contains_slot(item)
+                && items
+                    .iter()
+                    .enumerate()
+                    .any(|(other, term)| other != index && inner.contains(term))
+        })
+        .collect();
+    let mut kept: Vec<CompiledPredicate> = items
+        .into_iter()
+        .zip(absorbed)
+        .filter(|(_, absorbed)| !absorbed)
+        .map(|(item, _)| item)
+        .collect();
+
+    match kept.len() {
+        0 => identity,
+        1 => kept.remove(0),
+        _ if conjoin => CompiledPredicate::And { predicates: kept },
+        _ => CompiledPredicate::Or { predicates: kept },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eq(value: i64) -> CompiledPredicate {
+        CompiledPredicate::Eq {
+            value: json!(value),
+        }
+    }
+
+    fn not(predicate: CompiledPredicate) -> CompiledPredicate {
+        CompiledPredicate::Not {
+            predicate: Box::new(predicate),
+        }
+    }
+
+    #[test]
+    fn test_constants_double_negation_and_de_morgan() {
+        let simplified = simplify_predicate(&CompiledPredicate::And {
+            predicates: vec![
+                CompiledPredicate::True,
+                not(not(eq(1))),
+                not(CompiledPredicate::Or {
+                    predicates: vec![eq(2), eq(3)],
+                }),
+                eq(1),
+            ],
+        });
+        assert_eq!(
+            simplified,
+            CompiledPredicate::And {
+                predicates: vec![eq(1), not(eq(2)), not(eq(3))],
+            }
+        );
+
+        let simplified = simplify_predicate(&CompiledPredicate::Or {
+            predicates: vec![eq(1), CompiledPredicate::True, eq(2)],
+        });
+        assert_eq!(simplified, CompiledPredicate::True);
+
+        let simplified = simplify_predicate(&CompiledPredicate::And {
+            predicates: vec![CompiledPredicate::True],
+        });
+        assert_eq!(simplified, CompiledPredicate::True);
+    }
+
+    #[test]
+    fn test_absorption() {
+        let simplified = simplify_predicate(&CompiledPredicate::Or {
+            predicates: vec![
+                CompiledPredicate::And {
+                    predicates: vec![eq(1), eq(2)],
+                },
+                eq(1),
+            ],
+        });
+        assert_eq!(simplified, eq(1));
+    }
+
+    #[test]
+    fn test_slots_are_kept_in_order() {
+        let slot = |slot: u32| CompiledPredicate::Slot { slot };
+        let simplified = simplify_predicate(&CompiledPredicate::And {
+            predicates: vec![slot(0), slot(0), not(CompiledPredicate::True), slot(1)],
+        });
+        assert_eq!(
+            simplified,
+            CompiledPredicate::And {
+                predicates: vec![slot(0), slot(0), not(CompiledPredicate::True)],
+            }
+        );
+    }
+}
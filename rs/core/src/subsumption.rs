@@ -0,0 +1,1102 @@
+use serde_json::Value;
+
+use crate::compiler::{CompiledPredicate, contains_slot};
+use crate::evaluator::{evaluate_predicate, field, in_range, regex_matches, values_equal};
+
+const MAX_CLAUSES: usize = 256;
+const MAX_CASE_SPLITS: usize = 8;
+
+pub(crate) type Path = Vec<String>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Atom {
+    TypeOf(String),
+    IsNull,
+    IsUndefined,
+    Eq(Value),
+    OneOf(Vec<Value>),
+    StartsWith(String),
+    EndsWith(String),
+    Contains(String),
+    Regex {
+        pattern: String,
+        flags: Option<String>,
+    },
+    Range(Interval),
+    KeyCount(usize),
+    IsArray,
+    Length(Interval),
+    // Every array element from `start` on satisfies `predicate`.
+    Elements {
+        start: usize,
+        predicate: CompiledPredicate,
+    },
+    Slot(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Interval {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub min_inclusive: bool,
+    pub max_inclusive: bool,
+}
+
+impl Interval {
+    fn length(min: usize, max: Option<usize>) -> Interval {
+        Interval {
+            min: Some(min as f64),
+            max: max.map(|max| max as f64),
+            min_inclusive: true,
+            max_inclusive: true,
+        }
+    }
+
+    fn contains(&self, number: f64) -> bool {
+        in_range(
+            number,
+            self.min,
+            self.max,
+            self.min_inclusive,
+            self.max_inclusive,
+        )
+    }
+
+    fn intersect(&self, other: &Interval) -> Interval {
+        let (min, min_inclusive) = match (self.min, other.min) {
+            (Some(left), Some(right)) if left == right => {
+                (Some(left), self.min_inclusive && other.min_inclusive)
+            }
+            (Some(left), Some(right)) if left > right => (Some(left), self.min_inclusive),
+            (_, Some(right)) => (Some(right), other.min_inclusive),
+            (left, None) => (left, self.min_inclusive),
+        };
+        let (max, max_inclusive) = match (self.max, other.max) {
+            (Some(left), Some(right)) if left == right => {
+                (Some(left), self.max_inclusive && other.max_inclusive)
+            }
+            (Some(left), Some(right)) if left < right => (Some(left), self.max_inclusive),
+            (_, Some(right)) => (Some(right), other.max_inclusive),
+            (left, None) => (left, self.max_inclusive),
+        };
+        Interval {
+            min,
+            max,
+            min_inclusive,
+            max_inclusive,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => {
+                min > max || (min == max && !(self.min_inclusive && self.max_inclusive))
+            }
+            _ => false,
+        }
+    }
+
+    fn is_subset_of(&self, other: &Interval) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let lower = match (other.min, self.min) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(outer), Some(inner)) => {
+                outer < inner || (outer == inner && (other.min_inclusive || !self.min_inclusive))
+            }
+        };
+        let upper = match (other.max, self.max) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(outer), Some(inner)) => {
+                outer > inner || (outer == inner && (other.max_inclusive || !self.max_inclusive))
+            }
+        };
+        lower && upper
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Literal {
+    pub path: Path,
+    pub atom: Atom,
+    pub positive: bool,
+}
+
+pub(crate) type Clause = Vec<Literal>;
+
+// `previous` covers `current` when every value accepted by `current` is also
+// accepted by `previous`. Slot results are never guessed: a slot literal is only
+// implied by the very same slot at the same path.
+pub fn covers(previous: &CompiledPredicate, current: &CompiledPredicate) -> bool {
+    if matches!(previous, CompiledPredicate::True) || previous == current {
+        return true;
+    }
+
+    let (Some(previous), Some(current)) = (to_dnf(previous), to_dnf(current)) else {
+        return false;
+    };
+    current
+        .iter()
+        .all(|clause| entails(clause, &previous, MAX_CASE_SPLITS))
+}
+
+pub(crate) fn to_dnf(predicate: &CompiledPredicate) -> Option<Vec<Clause>> {
+    normalize(predicate, &Vec::new(), true)
+}
+
+fn normalize(predicate: &CompiledPredicate, path: &Path, positive: bool) -> Option<Vec<Clause>> {
+    let literal = |path: Path, atom: Atom| {
+        Some(vec![vec![Literal {
+            path,
+            atom,
+            positive,
+        }]])
+    };
+
+    match predicate {
+        CompiledPredicate::True => Some(if positive {
+            vec![Vec::new()]
+        } else {
+            Vec::new()
+        }),
+        CompiledPredicate::TypeOf { value } => literal(path.clone(), Atom::TypeOf(value.clone())),
+        CompiledPredicate::IsNull => literal(path.clone(), Atom::IsNull),
+        CompiledPredicate::IsUndefined => literal(path.clone(), Atom::IsUndefined),
+        CompiledPredicate::Eq { value } => literal(path.clone(), Atom::Eq(value.clone())),
+        CompiledPredicate::OneOf { values } => literal(path.clone(), Atom::OneOf(values.clone())),
+        CompiledPredicate::Slot { slot } => literal(path.clone(), Atom::Slot(*slot)),
+        CompiledPredicate::StartsWith { value } => {
+            literal(path.clone(), Atom::StartsWith(value.clone()))
+        }
+        CompiledPredicate::EndsWith { value } => {
+            literal(path.clone(), Atom::EndsWith(value.clone()))
+        }
+        CompiledPredicate::Contains { value } => {
+            literal(path.clone(), Atom::Contains(value.clone()))
+        }
+        CompiledPredicate::Regex { pattern, flags } => literal(
+            path.clone(),
+            Atom::Regex {
+                pattern: pattern.clone(),
+                flags: flags.clone(),
+            },
+        ),
+        CompiledPredicate::Range {
+            min,
+            max,
+            min_inclusive,
+            max_inclusive,
+        } => literal(
+            path.clone(),
+            Atom::Range(Interval {
+                min: *min,
+                max: *max,
+                min_inclusive: *min_inclusive,
+                max_inclusive: *max_inclusive,
+            }),
+        ),
+        CompiledPredicate::TagEq { key, value } => {
+            literal(child(path, key), Atom::Eq(value.clone()))
+        }
+        CompiledPredicate::Shape { fields, exact } => {
+            let mut parts = vec![normalize(
+                &CompiledPredicate::TypeOf {
+                    value: "object".to_string(),
+                },
+                path,
+                positive,
+            )?];
+            for (key, node) in fields {
+                parts.push(normalize(node, &child(path, key), positive)?);
+            }
+            if *exact {
+                parts.push(literal(path.clone(), Atom::KeyCount(fields.len()))?);
+            }
+            combine(parts, positive)
+        }
+        CompiledPredicate::Tuple { items, rest } => {
+            let length = items.len();
+            let mut parts = vec![
+                literal(path.clone(), Atom::IsArray)?,
+                literal(
+                    path.clone(),
+                    Atom::Length(Interval::length(length, rest.is_none().then_some(length))),
+                )?,
+            ];
+            for (index, node) in items.iter().enumerate() {
+                parts.push(normalize(node, &child(path, &index.to_string()), positive)?);
+            }
+            if let Some(rest) = rest.as_deref()
+                && *rest != CompiledPredicate::True
+            {
+                parts.push(literal(
+                    path.clone(),
+                    Atom::Elements {
+                        start: length,
+                        predicate: rest.clone(),
+                    },
+                )?);
+            }
+            combine(parts, positive)
+        }
+        CompiledPredicate::ArrayOf { predicate } => {
+            let mut parts = vec![literal(path.clone(), Atom::IsArray)?];
+            if **predicate != CompiledPredicate::True {
+                parts.push(literal(
+                    path.clone(),
+                    Atom::Elements {
+                        start: 0,
+                        predicate: (**predicate).clone(),
+                    },
+                )?);
+            }
+            combine(parts, positive)
+        }
+        CompiledPredicate::ArrayLength { length } => {
+            let parts = vec![
+                literal(path.clone(), Atom::IsArray)?,
+                literal(
+                    path.clone(),
+                    Atom::Length(Interval::length(*length, Some(*length))),
+                )?,
+            ];
+            combine(parts, positive)
+        }
+        CompiledPredicate::And { predicates } => {
+            let parts = predicates
+                .iter()
+                .map(|node| normalize(node, path, positive))
+                .collect::<Option<Vec<_>>>()?;
+            combine(parts, positive)
+        }
+        CompiledPredicate::Or { predicates } => {
+            let parts = predicates
+                .iter()
+                .map(|node| normalize(node, path, positive))
+                .collect::<Option<Vec<_>>>()?;
+            combine(parts, !positive)
+        }
+        CompiledPredicate::Not { predicate } => normalize(predicate, path, !positive),
+    }
+}
+
+// `conjoin` builds the cross product of the parts, otherwise their union.
+fn combine(parts: Vec<Vec<Clause>>, conjoin: bool) -> Option<Vec<Clause>> {
+    if !conjoin {
+        let clauses: Vec<Clause> = parts.into_iter().flatten().collect();
+        return (clauses.len() <= MAX_CLAUSES).then_some(clauses);
+    }
+
+    let mut clauses = vec![Vec::new()];
+    for part in parts {
+        let mut next = Vec::new();
+        for clause in &clauses {
+            for other in &part {
+                let mut merged: Clause = clause.clone();
+                merged.extend(other.iter().cloned());
+                next.push(merged);
+            }
+        }
+        if next.len() > MAX_CLAUSES {
+            return None;
+        }
+        clauses = next;
+    }
+    Some(clauses)
+}
+
+fn child(path: &Path, key: &str) -> Path {
+    let mut next = path.clone();
+    next.push(key.to_string());
+    next
+}
+
+fn entails(clause: &Clause, disjunction: &[Clause], splits_left: usize) -> bool {
+    if is_contradictory(clause) {
+        return true;
+    }
+
+    let implied = |target: &Clause| {
+        target
+            .iter()
+            .all(|literal| truth(clause, &literal.path, &literal.atom) == Some(literal.positive))
+    };
+    if disjunction.iter().any(implied) {
+        return true;
+    }
+    if splits_left == 0 {
+        return false;
+    }
+
+    let undecided = disjunction.iter().flatten().find(|literal| {
+        !matches!(literal.atom, Atom::Slot(_))
+            && truth(clause, &literal.path, &literal.atom).is_none()
+    });
+    let Some(undecided) = undecided else {
+        return false;
+    };
+
+    [true, false].into_iter().all(|positive| {
+        let mut branch = clause.clone();
+        branch.push(Literal {
+            path: undecided.path.clone(),
+            atom: undecided.atom.clone(),
+            positive,
+        });
+        entails(&branch, disjunction, splits_left - 1)
+    })
+}
+
+pub(crate) fn is_contradictory(clause: &Clause) -> bool {
+    conflicting_literal(clause).is_some() || exhausts_booleans(clause)
+}
+
+// Returns the index of the first literal the rest of the clause refutes.
+pub(crate) fn conflicting_literal(clause: &Clause) -> Option<usize> {
+    (0..clause.len()).find(|index| {
+        let literal = &clause[*index];
+        if matches!(literal.atom, Atom::Slot(_)) {
+            return false;
+        }
+        let rest: Clause = clause
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| other != index)
+            .map(|(_, item)| item.clone())
+            .collect();
+        truth(&rest, &literal.path, &literal.atom) == Some(!literal.positive)
+    })
+}
+
+// One description per DNF clause of a predicate that no value can satisfy, naming
+// the literals that contradict each other. `None` when some clause may hold.
+pub(crate) fn contradictions(predicate: &CompiledPredicate) -> Option<Vec<String>> {
+    to_dnf(predicate)?
+        .iter()
+        .map(|clause| {
+            let Some(index) = conflicting_literal(clause) else {
+                // Only a boolean ruled out as both `true` and `false` gets here.
+                return exhausts_booleans(clause).then(|| {
+                    clause
+                        .iter()
+                        .map(describe_literal)
+                        .collect::<Vec<_>>()
+                        .join(" and ")
+                });
+            };
+            let literal = &clause[index];
+            let partners: Vec<&Literal> = match clause.iter().enumerate().find(|(other, item)| {
+                *other != index
+                    && truth(&vec![(*item).clone()], &literal.path, &literal.atom)
+                        == Some(!literal.positive)
+            }) {
+                Some((_, item)) => vec![item],
+                None => clause
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .map(|(_, item)| item)
+                    .collect(),
+            };
+            let partners = partners
+                .into_iter()
+                .map(describe_literal)
+                .collect::<Vec<_>>()
+                .join(" and ");
+            Some(format!(
+                "{} conflicts with {}",
+                describe_literal(literal),
+                partners
+            ))
+        })
+        .collect()
+}
+
+fn describe_literal(literal: &Literal) -> String {
+    let path = std::iter::once("value")
+        .chain(literal.path.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(".");
+    let test = match &literal.atom {
+        Atom::TypeOf(type_name) => format!("typeof {} == \"{}\"", path, type_name),
+        Atom::IsNull => format!("{} == null", path),
+        Atom::IsUndefined => format!("{} == undefined", path),
+        Atom::Eq(value) => format!("{} == {}", path, value),
+        Atom::OneOf(values) => format!("{} in {}", path, Value::Array(values.clone())),
+        Atom::StartsWith(value) => format!("{} starts with {:?}", path, value),
+        Atom::EndsWith(value) => format!("{} ends with {:?}", path, value),
+        Atom::Contains(value) => format!("{} contains {:?}", path, value),
+        Atom::Regex { pattern, flags } => {
+            format!(
+                "{} matches /{}/{}",
+                path,
+                pattern,
+                flags.as_deref().unwrap_or("")
+            )
+        }
+        Atom::Range(interval) => format!("{} in {}", path, describe_interval(interval)),
+        Atom::KeyCount(count) => format!("{} has {} keys", path, count),
+        Atom::IsArray => format!("{} is an array", path),
+        Atom::Length(interval) => {
+            format!("{}.length in {}", path, describe_interval(interval))
+        }
+        Atom::Elements { start, .. } => {
+            format!("{} elements from index {} match", path, start)
+        }
+        Atom::Slot(slot) => format!("slot {}({})", slot, path),
+    };
+    if literal.positive {
+        test
+    } else {
+        format!("!({})", test)
+    }
+}
+
+fn describe_interval(interval: &Interval) -> String {
+    let bound = |bound: Option<f64>, infinity: &str| {
+        bound.map_or_else(|| infinity.to_string(), |number| number.to_string())
+    };
+    format!(
+        "{}{}, {}{}",
+        if interval.min_inclusive { "[" } else { "(" },
+        bound(interval.min, "-Infinity"),
+        bound(interval.max, "Infinity"),
+        if interval.max_inclusive { "]" } else { ")" },
+    )
+}
+
+fn exhausts_booleans(clause: &Clause) -> bool {
+    clause.iter().any(|literal| {
+        known_type(clause, &literal.path) == Some("boolean")
+            && [true, false].iter().all(|flag| {
+                clause.iter().any(|other| {
+                    other.path == literal.path
+                        && !other.positive
+                        && other.atom == Atom::Eq(Value::Bool(*flag))
+                })
+            })
+    })
+}
+
+// Whether `atom` at `path` is decided by the literals of `clause`.
+pub(crate) fn truth(clause: &Clause, path: &Path, atom: &Atom) -> Option<bool> {
+    if let Some(literal) = clause
+        .iter()
+        .find(|literal| literal.path == *path && literal.atom == *atom)
+    {
+        return Some(literal.positive);
+    }
+    if matches!(atom, Atom::Slot(_)) {
+        return None;
+    }
+    if let Some(value) = known_value(clause, path)
+        && let Some(result) = atom_holds(atom, value.as_ref())
+    {
+        return Some(result);
+    }
+    if let Some(result) = set_truth(clause, path, atom) {
+        return Some(result);
+    }
+
+    let required = required_type(atom);
+    if let Some(actual) = known_type(clause, path) {
+        if required.is_some_and(|required| actual != required) {
+            return Some(false);
+        }
+        if let Atom::TypeOf(_) | Atom::IsNull | Atom::IsUndefined = atom {
+            return Some(true);
+        }
+    }
+    if let Some(result) = array_truth(clause, path, atom) {
+        return Some(result);
+    }
+    if let Some(result) = string_truth(clause, path, atom) {
+        return Some(result);
+    }
+    if let Some(interval) = known_interval(clause, path, range_of) {
+        match atom {
+            Atom::Range(range) if interval.is_subset_of(range) => return Some(true),
+            Atom::Range(range) if interval.intersect(range).is_empty() => return Some(false),
+            Atom::Eq(Value::Number(number))
+                if number
+                    .as_f64()
+                    .is_some_and(|number| !interval.contains(number)) =>
+            {
+                return Some(false);
+            }
+            _ => {}
+        }
+    }
+    if required.is_some_and(|required| excludes_type(clause, path, required)) {
+        return Some(false);
+    }
+    if let Atom::KeyCount(count) = atom {
+        let other_count = clause.iter().any(|literal| {
+            literal.path == *path
+                && literal.positive
+                && matches!(literal.atom, Atom::KeyCount(other) if other != *count)
+        });
+        if other_count {
+            return Some(false);
+        }
+    }
+    None
+}
+
+// The concrete value at `path` when an `Eq` literal on it or an ancestor fixes it.
+// `Some(None)` means the value is known to be undefined.
+fn known_value(clause: &Clause, path: &Path) -> Option<Option<Value>> {
+    clause.iter().find_map(|literal| {
+        let Atom::Eq(value) = &literal.atom else {
+            return None;
+        };
+        if !literal.positive || !path.starts_with(&literal.path) {
+            return None;
+        }
+        let mut current = Some(value);
+        for key in &path[literal.path.len()..] {
+            current = current.and_then(|item| field(item, key));
+        }
+        Some(current.cloned())
+    })
+}
+
+// Decides `atom` against the values that positive `OneOf` literals on `path`
+// still allow once every other literal on `path` has ruled its members out.
+fn set_truth(clause: &Clause, path: &Path, atom: &Atom) -> Option<bool> {
+    let on_path = || clause.iter().filter(|literal| literal.path == *path);
+    let allowed = |value: &Value| {
+        on_path().all(|literal| atom_holds(&literal.atom, Some(value)) != Some(!literal.positive))
+    };
+
+    let known = on_path()
+        .filter(|literal| literal.positive)
+        .filter_map(|literal| match &literal.atom {
+            Atom::OneOf(values) => Some(values.clone()),
+            _ => None,
+        })
+        .reduce(|left, right| {
+            left.into_iter()
+                .filter(|value| right.iter().any(|item| values_equal(item, value)))
+                .collect()
+        });
+    if let Some(known) = known {
+        let mut results = known
+            .iter()
+            .filter(|value| allowed(value))
+            .map(|value| atom_holds(atom, Some(value)));
+        let first = results.next().unwrap_or(Some(true));
+        if first.is_some() && results.all(|result| result == first) {
+            return first;
+        }
+    }
+
+    match atom {
+        Atom::OneOf(values) if !values.iter().any(allowed) => Some(false),
+        _ => None,
+    }
+}
+
+fn array_truth(clause: &Clause, path: &Path, atom: &Atom) -> Option<bool> {
+    let on_path = |literal: &&Literal| literal.path == *path;
+    let is_array_literal = |literal: &Literal| {
+        matches!(
+            literal.atom,
+            Atom::IsArray | Atom::Length(_) | Atom::Elements { .. }
+        )
+    };
+
+    match atom {
+        Atom::IsArray => clause
+            .iter()
+            .filter(on_path)
+            .any(|literal| literal.positive && is_array_literal(literal))
+            .then_some(true),
+        Atom::Length(_) | Atom::Elements { .. }
+            if clause
+                .iter()
+                .filter(on_path)
+                .any(|literal| !literal.positive && literal.atom == Atom::IsArray) =>
+        {
+            Some(false)
+        }
+        Atom::Length(length) => {
+            let known = known_interval(clause, path, length_of)?;
+            if known.is_subset_of(length) {
+                Some(true)
+            } else if known.intersect(length).is_empty() {
+                Some(false)
+            } else {
+                None
+            }
+        }
+        Atom::Elements { start, predicate } => clause
+            .iter()
+            .filter(on_path)
+            .any(|literal| {
+                matches!(
+                    &literal.atom,
+                    Atom::Elements { start: other_start, predicate: other }
+                        if literal.positive && other_start <= start && covers(predicate, other)
+                )
+            })
+            .then_some(true),
+        _ => None,
+    }
+}
+
+fn string_truth(clause: &Clause, path: &Path, atom: &Atom) -> Option<bool> {
+    let literals = || clause.iter().filter(|literal| literal.path == *path);
+
+    for literal in literals() {
+        let decided = match (&literal.atom, atom, literal.positive) {
+            (Atom::StartsWith(known), Atom::StartsWith(wanted), true) => {
+                if known.starts_with(wanted.as_str()) {
+                    Some(true)
+                } else if !wanted.starts_with(known.as_str()) {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            (Atom::EndsWith(known), Atom::EndsWith(wanted), true) => {
+                if known.ends_with(wanted.as_str()) {
+                    Some(true)
+                } else if !wanted.ends_with(known.as_str()) {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            (
+                Atom::StartsWith(known) | Atom::EndsWith(known) | Atom::Contains(known),
+                Atom::Contains(wanted),
+                true,
+            ) if known.contains(wanted.as_str()) => Some(true),
+            // A string lacking `known` cannot carry anything that contains it.
+            (Atom::StartsWith(known), Atom::StartsWith(wanted), false)
+                if wanted.starts_with(known.as_str()) =>
+            {
+                Some(false)
+            }
+            (Atom::EndsWith(known), Atom::EndsWith(wanted), false)
+                if wanted.ends_with(known.as_str()) =>
+            {
+                Some(false)
+            }
+            (
+                Atom::Contains(known),
+                Atom::StartsWith(wanted) | Atom::EndsWith(wanted) | Atom::Contains(wanted),
+                false,
+            ) if wanted.contains(known.as_str()) => Some(false),
+            _ => None,
+        };
+        if decided.is_some() {
+            return decided;
+        }
+    }
+    None
+}
+
+fn range_of(atom: &Atom) -> Option<Interval> {
+    match atom {
+        Atom::Range(interval) => Some(*interval),
+        _ => None,
+    }
+}
+
+fn length_of(atom: &Atom) -> Option<Interval> {
+    match atom {
+        Atom::Length(interval) => Some(*interval),
+        _ => None,
+    }
+}
+
+// The intersection of every positive interval literal of one kind on `path`.
+fn known_interval(
+    clause: &Clause,
+    path: &Path,
+    interval_of: fn(&Atom) -> Option<Interval>,
+) -> Option<Interval> {
+    clause
+        .iter()
+        .filter(|literal| literal.path == *path && literal.positive)
+        .filter_map(|literal| interval_of(&literal.atom))
+        .reduce(|left, right| left.intersect(&right))
+}
+
+fn known_type(clause: &Clause, path: &Path) -> Option<&'static str> {
+    if let Some(value) = known_value(clause, path) {
+        return Some(type_of(value.as_ref()));
+    }
+    clause.iter().find_map(|literal| {
+        if literal.path == *path && literal.positive {
+            return required_type(&literal.atom);
+        }
+        // A defined descendant means this path holds an object.
+        let is_descendant = literal.path.len() > path.len() && literal.path.starts_with(path);
+        let defines = match literal.atom {
+            Atom::IsUndefined => !literal.positive,
+            Atom::Slot(_) => false,
+            _ => literal.positive,
+        };
+        (is_descendant && defines).then_some("object")
+    })
+}
+
+fn excludes_type(clause: &Clause, path: &Path, type_name: &str) -> bool {
+    clause.iter().any(|literal| {
+        literal.path == *path
+            && !literal.positive
+            && match &literal.atom {
+                Atom::TypeOf(value) => value == type_name,
+                Atom::IsNull => type_name == "null",
+                Atom::IsUndefined => type_name == "undefined",
+                _ => false,
+            }
+    })
+}
+
+// `None` when the atom does not pin down a single type.
+fn required_type(atom: &Atom) -> Option<&'static str> {
+    let type_name = match atom {
+        Atom::TypeOf(value) => match value.as_str() {
+            "number" => "number",
+            "string" => "string",
+            "boolean" => "boolean",
+            "object" => "object",
+            _ => "unknown",
+        },
+        Atom::IsNull => "null",
+        Atom::IsUndefined => "undefined",
+        Atom::Eq(value) => type_of(Some(value)),
+        Atom::OneOf(values) => {
+            let mut types = values.iter().map(|value| type_of(Some(value)));
+            let first = types.next()?;
+            return types.all(|item| item == first).then_some(first);
+        }
+        Atom::StartsWith(_) | Atom::EndsWith(_) | Atom::Contains(_) | Atom::Regex { .. } => {
+            "string"
+        }
+        Atom::Range(_) => "number",
+        Atom::KeyCount(_) | Atom::IsArray | Atom::Length(_) | Atom::Elements { .. } => "object",
+        Atom::Slot(_) => return None,
+    };
+    Some(type_name)
+}
+
+fn type_of(value: Option<&Value>) -> &'static str {
+    match value {
+        None => "undefined",
+        Some(Value::Null) => "null",
+        Some(Value::Bool(_)) => "boolean",
+        Some(Value::Number(_)) => "number",
+        Some(Value::String(_)) => "string",
+        Some(Value::Array(_)) | Some(Value::Object(_)) => "object",
+    }
+}
+
+// `None` when the answer depends on a slot.
+fn atom_holds(atom: &Atom, value: Option<&Value>) -> Option<bool> {
+    let result = match atom {
+        Atom::Eq(expected) => value.is_some_and(|actual| values_equal(actual, expected)),
+        Atom::OneOf(values) => {
+            value.is_some_and(|actual| values.iter().any(|item| values_equal(actual, item)))
+        }
+        Atom::KeyCount(count) => match value {
+            Some(Value::Object(map)) => map.len() == *count,
+            Some(Value::Array(items)) => items.len() == *count,
+            _ => false,
+        },
+        Atom::Range(interval) => value
+            .and_then(Value::as_f64)
+            .is_some_and(|number| interval.contains(number)),
+        Atom::StartsWith(prefix) => value
+            .and_then(Value::as_str)
+            .is_some_and(|text| text.starts_with(prefix.as_str())),
+        Atom::EndsWith(suffix) => value
+            .and_then(Value::as_str)
+            .is_some_and(|text| text.ends_with(suffix.as_str())),
+        Atom::Contains(needle) => value
+            .and_then(Value::as_str)
+            .is_some_and(|text| text.contains(needle.as_str())),
+        Atom::Regex { pattern, flags } => match value.and_then(Value::as_str) {
+            Some(text) => return regex_matches(pattern, flags.as_deref(), text),
+            None => false,
+        },
+        Atom::IsArray => matches!(value, Some(Value::Array(_))),
+        Atom::Length(interval) => {
+            matches!(value, Some(Value::Array(items)) if interval.contains(items.len() as f64))
+        }
+        Atom::Elements { start, predicate } => {
+            let Some(Value::Array(items)) = value else {
+                return Some(false);
+            };
+            if contains_slot(predicate) {
+                return None;
+            }
+            items
+                .iter()
+                .skip(*start)
+                .all(|item| evaluate_predicate(predicate, Some(item), &mut |_, _| false))
+        }
+        Atom::Slot(_) => return None,
+        atom => Some(type_of(value)) == required_type(atom),
+    };
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    fn tag(key: &str, value: Value) -> CompiledPredicate {
+        CompiledPredicate::TagEq {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    fn type_of(value: &str) -> CompiledPredicate {
+        CompiledPredicate::TypeOf {
+            value: value.to_string(),
+        }
+    }
+
+    fn shape(fields: Vec<(&str, CompiledPredicate)>, exact: bool) -> CompiledPredicate {
+        CompiledPredicate::Shape {
+            fields: fields
+                .into_iter()
+                .map(|(key, node)| (key.to_string(), node))
+                .collect::<BTreeMap<_, _>>(),
+            exact,
+        }
+    }
+
+    #[test]
+    fn test_or_on_either_side() {
+        let previous = CompiledPredicate::Or {
+            predicates: vec![type_of("number"), type_of("string")],
+        };
+        let current = CompiledPredicate::Or {
+            predicates: vec![
+                CompiledPredicate::Eq { value: json!(1) },
+                CompiledPredicate::Eq { value: json!("a") },
+            ],
+        };
+
+        assert!(covers(&previous, &current));
+        assert!(!covers(&current, &previous));
+    }
+
+    #[test]
+    fn test_tag_against_shape_and_typeof() {
+        let current = shape(
+            vec![(
+                "type",
+                CompiledPredicate::Eq {
+                    value: json!("pay"),
+                },
+            )],
+            true,
+        );
+
+        assert!(covers(&tag("type", json!("pay")), &current));
+        assert!(covers(&type_of("object"), &current));
+        assert!(covers(
+            &shape(vec![("type", type_of("string"))], false),
+            &tag("type", json!("pay"))
+        ));
+        assert!(!covers(&tag("type", json!("refund")), &current));
+    }
+
+    #[test]
+    fn test_not_and_case_splits() {
+        let not_null = CompiledPredicate::Not {
+            predicate: Box::new(CompiledPredicate::IsNull),
+        };
+        assert!(covers(&not_null, &type_of("number")));
+        assert!(covers(&not_null, &CompiledPredicate::IsUndefined));
+        assert!(!covers(&type_of("number"), &not_null));
+
+        let either_flag = CompiledPredicate::Or {
+            predicates: vec![
+                CompiledPredicate::Eq { value: json!(true) },
+                CompiledPredicate::Eq {
+                    value: json!(false),
+                },
+            ],
+        };
+        assert!(covers(&either_flag, &type_of("boolean")));
+
+        let tautology = CompiledPredicate::Or {
+            predicates: vec![
+                type_of("number"),
+                CompiledPredicate::Not {
+                    predicate: Box::new(type_of("number")),
+                },
+            ],
+        };
+        assert!(covers(&tautology, &CompiledPredicate::IsNull));
+    }
+
+    #[test]
+    fn test_slots_are_never_assumed() {
+        let slot = CompiledPredicate::Slot { slot: 0 };
+        let guarded = CompiledPredicate::And {
+            predicates: vec![tag("type", json!("pay")), slot.clone()],
+        };
+
+        assert!(!covers(&guarded, &tag("type", json!("pay"))));
+        assert!(covers(&tag("type", json!("pay")), &guarded));
+        assert!(!covers(
+            &CompiledPredicate::Or {
+                predicates: vec![
+                    slot.clone(),
+                    CompiledPredicate::Not {
+                        predicate: Box::new(slot),
+                    },
+                ],
+            },
+            &type_of("number")
+        ));
+    }
+
+    #[test]
+    fn test_contradictions() {
+        let clause = to_dnf(&CompiledPredicate::And {
+            predicates: vec![tag("type", json!("pay")), tag("type", json!("refund"))],
+        })
+        .unwrap();
+        assert!(is_contradictory(&clause[0]));
+
+        let clause = to_dnf(&CompiledPredicate::And {
+            predicates: vec![type_of("number"), type_of("string")],
+        })
+        .unwrap();
+        assert!(is_contradictory(&clause[0]));
+    }
+
+    #[test]
+    fn test_array_patterns() {
+        let numbers = CompiledPredicate::ArrayOf {
+            predicate: Box::new(type_of("number")),
+        };
+        let scalars = CompiledPredicate::ArrayOf {
+            predicate: Box::new(CompiledPredicate::Or {
+                predicates: vec![type_of("number"), type_of("string")],
+            }),
+        };
+        assert!(covers(&scalars, &numbers));
+        assert!(!covers(&numbers, &scalars));
+
+        let head_then_anything = CompiledPredicate::Tuple {
+            items: vec![CompiledPredicate::Eq {
+                value: json!("add"),
+            }],
+            rest: Some(Box::new(CompiledPredicate::True)),
+        };
+        let pair = CompiledPredicate::Tuple {
+            items: vec![
+                CompiledPredicate::Eq {
+                    value: json!("add"),
+                },
+                type_of("number"),
+            ],
+            rest: None,
+        };
+        assert!(covers(&head_then_anything, &pair));
+        assert!(covers(&CompiledPredicate::ArrayLength { length: 2 }, &pair));
+        assert!(!covers(&pair, &head_then_anything));
+
+        let clause = to_dnf(&CompiledPredicate::And {
+            predicates: vec![CompiledPredicate::ArrayLength { length: 1 }, pair.clone()],
+        })
+        .unwrap();
+        assert!(is_contradictory(&clause[0]));
+    }
+
+    #[test]
+    fn test_string_predicates() {
+        let starts_with = |value: &str| CompiledPredicate::StartsWith {
+            value: value.to_string(),
+        };
+        let paid = CompiledPredicate::Eq {
+            value: json!("order.paid"),
+        };
+
+        assert!(covers(&starts_with("order."), &starts_with("order.paid")));
+        assert!(!covers(&starts_with("order.paid"), &starts_with("order.")));
+        assert!(covers(&starts_with("order.paid"), &paid));
+        assert!(covers(&starts_with("order."), &paid));
+        assert!(covers(
+            &CompiledPredicate::Contains {
+                value: "paid".to_string()
+            },
+            &starts_with("order.paid")
+        ));
+        assert!(covers(&type_of("string"), &starts_with("order.")));
+
+        let regex = CompiledPredicate::Regex {
+            pattern: "^order\\.".to_string(),
+            flags: None,
+        };
+        assert!(covers(&regex, &paid));
+        assert!(!covers(&regex, &starts_with("order.")));
+
+        let clause = to_dnf(&CompiledPredicate::And {
+            predicates: vec![starts_with("order."), starts_with("user.")],
+        })
+        .unwrap();
+        assert!(is_contradictory(&clause[0]));
+    }
+
+    #[test]
+    fn test_value_sets() {
+        let one_of = |values: Value| CompiledPredicate::OneOf {
+            values: values.as_array().unwrap().clone(),
+        };
+        let settled = one_of(json!(["paid", "settled", "refunded"]));
+
+        assert!(covers(&settled, &one_of(json!(["refunded", "paid"]))));
+        assert!(covers(
+            &settled,
+            &CompiledPredicate::Eq {
+                value: json!("paid")
+            }
+        ));
+        assert!(!covers(&settled, &one_of(json!(["paid", "open"]))));
+        assert!(covers(&type_of("string"), &settled));
+        assert!(covers(
+            &CompiledPredicate::Or {
+                predicates: vec![one_of(json!([1, 2])), type_of("string")],
+            },
+            &one_of(json!([1, "x"]))
+        ));
+
+        let clause = to_dnf(&CompiledPredicate::And {
+            predicates: vec![
+                one_of(json!(["paid", "open"])),
+                one_of(json!(["open", "void"])),
+            ],
+        })
+        .unwrap();
+        assert!(!is_contradictory(&clause[0]));
+
+        let clause = to_dnf(&CompiledPredicate::And {
+            predicates: vec![
+                one_of(json!(["paid", "open"])),
+                CompiledPredicate::Not {
+                    predicate: Box::new(one_of(json!(["open", "paid"]))),
+                },
+            ],
+        })
+        .unwrap();
+        assert!(is_contradictory(&clause[0]));
+    }
+}
@@ -0,0 +1,137 @@
+#[allow(unused_mut, unused_parens, unused_variables, clippy::all)]
+pub fn route<S>(value: Option<&serde_json::Value>, mut slots: S) -> Option<usize>
+where
+    S: FnMut(u32, Option<&serde_json::Value>) -> bool,
+{
+    use serde_json::Value;
+    use std::sync::LazyLock;
+    // Arrays behave like JS objects keyed by their indices.
+    fn field<'a>(value: Option<&'a Value>, key: &str) -> Option<&'a Value> {
+        match value? {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => {
+                let is_canonical = key == "0" || !key.starts_with('0');
+                if !is_canonical || key.is_empty() || !key.chars().all(|ch| ch.is_ascii_digit()) {
+                    return None;
+                }
+                key.parse::<usize>().ok().and_then(|index| items.get(index))
+            }
+            _ => None,
+        }
+    }
+    fn key_count(value: Option<&Value>) -> Option<usize> {
+        match value? {
+            Value::Object(map) => Some(map.len()),
+            Value::Array(items) => Some(items.len()),
+            _ => None,
+        }
+    }
+    // Numbers compare by value, so `1` equals `1.0`.
+    fn values_equal(left: &Value, right: &Value) -> bool {
+        match (left, right) {
+            (Value::Number(left), Value::Number(right)) => left.as_f64() == right.as_f64(),
+            (Value::Array(left), Value::Array(right)) => {
+                left.len() == right.len()
+                    && left.iter().zip(right).all(|(left, right)| values_equal(left, right))
+            }
+            (Value::Object(left), Value::Object(right)) => {
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .all(|(key, left)| right.get(key).is_some_and(|right| values_equal(left, right)))
+            }
+            _ => left == right,
+        }
+    }
+    static CONSTANT_0: LazyLock<Value> =
+        LazyLock::new(|| serde_json::from_str("{\"a\":[1,2]}").unwrap());
+    static REGEX_0: LazyLock<regex::Regex> =
+        LazyLock::new(|| regex::Regex::new("(?i)^ab+c$").unwrap());
+
+    if (matches!(value, Some(Value::Object(_) | Value::Array(_))) && field(value, "amount").and_then(Value::as_f64).is_some_and(|number| number > 0.0_f64) && field(value, "type").and_then(Value::as_str) == Some("pay")) {
+        return Some(0);
+    }
+    if (matches!(value, Some(Value::Object(_) | Value::Array(_))) && field(value, "reason").and_then(Value::as_str).is_some_and(|text| text.starts_with("dup")) && field(value, "type").and_then(Value::as_str) == Some("refund")) {
+        return Some(1);
+    }
+    if (matches!(value, Some(Value::Object(_) | Value::Array(_))) && (field(value, "id").and_then(Value::as_f64) == Some(1.0_f64) || field(value, "id").and_then(Value::as_f64) == Some(2.0_f64) || field(value, "id").and_then(Value::as_f64) == Some(3.0_f64)) && key_count(value) == Some(1)) {
+        return Some(2);
+    }
+    if (matches!(value, Some(Value::Array(items0)) if items0.len() >= 1) && matches!(field(value, "0"), Some(Value::String(_))) && value.and_then(Value::as_array).is_some_and(|items0| items0[1..].iter().all(|element0| matches!(Some(element0), Some(Value::Number(_)))))) {
+        return Some(3);
+    }
+    if value.and_then(Value::as_array).is_some_and(|items0| items0.iter().all(|element0| field(Some(element0), "ok").and_then(Value::as_bool) == Some(true))) {
+        return Some(4);
+    }
+    if value.and_then(Value::as_str).is_some_and(|text| REGEX_0.is_match(text)) {
+        return Some(5);
+    }
+    if value.is_some_and(|item| values_equal(item, &CONSTANT_0)) {
+        return Some(6);
+    }
+    if (!(matches!(value, Some(Value::Null))) && slots(0, value)) {
+        return Some(7);
+    }
+    if matches!(value, Some(Value::Null)) {
+        return Some(8);
+    }
+    None
+}
+
+#[allow(unused_mut, unused_parens, unused_variables, clippy::all)]
+pub fn dispatch<S>(value: Option<&serde_json::Value>, mut slots: S) -> Option<usize>
+where
+    S: FnMut(u32, Option<&serde_json::Value>) -> bool,
+{
+    use serde_json::Value;
+    // Arrays behave like JS objects keyed by their indices.
+    fn field<'a>(value: Option<&'a Value>, key: &str) -> Option<&'a Value> {
+        match value? {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => {
+                let is_canonical = key == "0" || !key.starts_with('0');
+                if !is_canonical || key.is_empty() || !key.chars().all(|ch| ch.is_ascii_digit()) {
+                    return None;
+                }
+                key.parse::<usize>().ok().and_then(|index| items.get(index))
+            }
+            _ => None,
+        }
+    }
+
+    match field(value, "type").and_then(Value::as_str) {
+        Some("pay\u{1b}") => {
+            if field(value, "type").and_then(Value::as_str) == Some("pay\u{1b}") {
+                return Some(0);
+            }
+            if true {
+                return Some(3);
+            }
+            None
+        }
+        Some("ré\"fund\u{2028}") => {
+            if (field(value, "type").and_then(Value::as_str) == Some("ré\"fund\u{2028}") && (matches!(value, Some(Value::Object(_) | Value::Array(_))) && field(value, "amount").and_then(Value::as_f64).is_some_and(|number| number < 10.0_f64))) {
+                return Some(1);
+            }
+            if true {
+                return Some(3);
+            }
+            None
+        }
+        Some("\\{}") => {
+            if field(value, "type").and_then(Value::as_str) == Some("\\{}") {
+                return Some(2);
+            }
+            if true {
+                return Some(3);
+            }
+            None
+        }
+        _ => {
+            if true {
+                return Some(3);
+            }
+            None
+        }
+    }
+}
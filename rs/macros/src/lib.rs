@@ -0,0 +1,669 @@
+use std::collections::BTreeMap;
+
+use match_pattern_core::{
+    BranchAst, CompileOptions, MatchProgram, PredicateAst, Severity, compile_program_with_options,
+};
+use proc_macro2::{Literal, Span, TokenStream};
+use quote::quote;
+use serde_json::{Map, Value};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    Attribute, Error, Ident, Lit, LitInt, LitStr, Token, braced, bracketed, parenthesized, token,
+};
+
+// `match_program! { tag("type", "pay") => 0, shape { amount: gt(0) } => 1, _ => 2 }`
+// expands to a `MatchProgram`. The program is compiled during expansion and every
+// `unreachable_branch` diagnostic becomes a compile error on that branch, unless the
+// branch is marked `#[allow(unreachable_branch)]`.
+#[proc_macro]
+pub fn match_program(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand(input.into())
+        .unwrap_or_else(|error| {
+            // Several `compile_error!`s are only valid in expression position inside a block.
+            let errors = error.into_compile_error();
+            quote!({ #errors })
+        })
+        .into()
+}
+
+fn expand(input: TokenStream) -> syn::Result<TokenStream> {
+    let Program { program, spans } = syn::parse2(input)?;
+
+    let options = CompileOptions {
+        severity: BTreeMap::from([("unreachable_branch".to_string(), Severity::Error)]),
+        ..CompileOptions::default()
+    };
+    let plan = compile_program_with_options(&program, &options);
+    let mut errors = plan
+        .diagnostics
+        .iter()
+        .filter(|item| item.level == Severity::Error)
+        .map(|item| {
+            let span = item
+                .branch_index
+                .map_or_else(Span::call_site, |index| spans[index]);
+            Error::new(span, &item.message)
+        });
+    if let Some(mut error) = errors.next() {
+        errors.for_each(|other| error.combine(other));
+        return Err(error);
+    }
+
+    let branches = program.branches.iter().map(|branch| {
+        let action_index = match branch.action_index {
+            Some(index) => {
+                let index = Literal::usize_unsuffixed(index);
+                quote!(::core::option::Option::Some(#index))
+            }
+            None => quote!(::core::option::Option::None),
+        };
+        let predicate = predicate_tokens(&branch.predicate);
+        let allow = &branch.allow;
+        quote! {
+            ::match_pattern_rs::BranchAst {
+                action_index: #action_index,
+                predicate: #predicate,
+                allow: ::std::vec![#(::std::string::String::from(#allow)),*],
+            }
+        }
+    });
+    Ok(quote! {
+        ::match_pattern_rs::MatchProgram {
+            branches: ::std::vec![#(#branches),*],
+            input_schema: ::core::option::Option::None,
+        }
+    })
+}
+
+// The parsed program, plus the span of each branch's pattern for error reporting.
+struct Program {
+    program: MatchProgram,
+    spans: Vec<Span>,
+}
+
+impl Parse for Program {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut branches = Vec::new();
+        let mut spans = Vec::new();
+        while !input.is_empty() {
+            let allow = parse_allow(input)?;
+            spans.push(input.span());
+            let predicate = parse_alternatives(input)?;
+            input.parse::<Token![=>]>()?;
+            let action_index = input.parse::<LitInt>()?.base10_parse::<usize>()?;
+            branches.push(BranchAst {
+                action_index: Some(action_index),
+                predicate,
+                allow,
+            });
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(Program {
+            program: MatchProgram {
+                branches,
+                input_schema: None,
+            },
+            spans,
+        })
+    }
+}
+
+// `#[allow(code, ...)]` attributes in front of a branch.
+fn parse_allow(input: ParseStream) -> syn::Result<Vec<String>> {
+    let mut codes = Vec::new();
+    for attribute in input.call(Attribute::parse_outer)? {
+        if !attribute.path().is_ident("allow") {
+            return Err(Error::new_spanned(attribute, "expected `#[allow(...)]`"));
+        }
+        attribute.parse_nested_meta(|meta| {
+            let code = meta.path.require_ident()?;
+            codes.push(code.to_string());
+            Ok(())
+        })?;
+    }
+    Ok(codes)
+}
+
+// `a | b | c` is an `or` of the alternatives.
+fn parse_alternatives(input: ParseStream) -> syn::Result<PredicateAst> {
+    let mut predicates = vec![parse_pattern(input)?];
+    while input.peek(Token![|]) {
+        input.parse::<Token![|]>()?;
+        predicates.push(parse_pattern(input)?);
+    }
+    Ok(if predicates.len() == 1 {
+        predicates.remove(0)
+    } else {
+        PredicateAst::Or { predicates }
+    })
+}
+
+fn parse_pattern(input: ParseStream) -> syn::Result<PredicateAst> {
+    if input.peek(Token![_]) {
+        input.parse::<Token![_]>()?;
+        return Ok(PredicateAst::Wildcard);
+    }
+    if input.peek(token::Bracket) {
+        return parse_tuple(input);
+    }
+    if input.peek(Lit) || input.peek(Token![-]) {
+        return Ok(PredicateAst::Eq {
+            value: parse_value(input)?,
+        });
+    }
+
+    let name: Ident = input.parse()?;
+    if input.peek(Token![@]) {
+        input.parse::<Token![@]>()?;
+        return Ok(PredicateAst::Bind {
+            name: name.to_string(),
+            predicate: Box::new(parse_pattern(input)?),
+        });
+    }
+
+    let predicate = match name.to_string().as_str() {
+        "is_number" => PredicateAst::IsNumber,
+        "is_string" => PredicateAst::IsString,
+        "is_boolean" => PredicateAst::IsBoolean,
+        "is_null" => PredicateAst::IsNull,
+        "is_undefined" => PredicateAst::IsUndefined,
+        "shape" | "exact" => PredicateAst::Shape {
+            fields: parse_fields(input)?,
+            exact: name == "exact",
+        },
+        "eq" => PredicateAst::Eq {
+            value: single(input, parse_value)?,
+        },
+        "tag" => {
+            let content;
+            parenthesized!(content in input);
+            let key = content.parse::<LitStr>()?.value();
+            content.parse::<Token![,]>()?;
+            let value = parse_value(&content)?;
+            end_of_arguments(&content)?;
+            PredicateAst::Tag { key, value }
+        }
+        "one_of" => PredicateAst::OneOf {
+            values: arguments(input, parse_value)?,
+        },
+        "starts_with" => PredicateAst::StartsWith {
+            value: single(input, parse_string)?,
+        },
+        "ends_with" => PredicateAst::EndsWith {
+            value: single(input, parse_string)?,
+        },
+        "contains" => PredicateAst::Contains {
+            value: single(input, parse_string)?,
+        },
+        "regex" => {
+            let mut strings = arguments(input, parse_string)?.into_iter();
+            match (strings.next(), strings.next(), strings.next()) {
+                (Some(pattern), flags, None) => PredicateAst::Regex { pattern, flags },
+                _ => return Err(Error::new(name.span(), "expected `regex(pattern, flags?)`")),
+            }
+        }
+        "gt" | "gte" | "lt" | "lte" => {
+            let bound = Some(single(input, parse_number)?);
+            let lower = name == "gt" || name == "gte";
+            let inclusive = name == "gte" || name == "lte";
+            PredicateAst::Range {
+                min: if lower { bound } else { None },
+                max: if lower { None } else { bound },
+                min_inclusive: lower && inclusive,
+                max_inclusive: !lower && inclusive,
+            }
+        }
+        "array_of" => PredicateAst::ArrayOf {
+            predicate: Box::new(single(input, parse_alternatives)?),
+        },
+        "array_length" => PredicateAst::ArrayLength {
+            length: single(input, |content| {
+                content.parse::<LitInt>()?.base10_parse::<usize>()
+            })?,
+        },
+        "and" => PredicateAst::And {
+            predicates: arguments(input, parse_alternatives)?,
+        },
+        "or" => PredicateAst::Or {
+            predicates: arguments(input, parse_alternatives)?,
+        },
+        "not" => PredicateAst::Not {
+            predicate: Box::new(single(input, parse_alternatives)?),
+        },
+        "slot" => PredicateAst::Slot {
+            slot: single(input, |content| {
+                content.parse::<LitInt>()?.base10_parse::<u32>()
+            })?,
+        },
+        _ => {
+            return Err(Error::new(
+                name.span(),
+                format!("unknown pattern `{}`", name),
+            ));
+        }
+    };
+    Ok(predicate)
+}
+
+// `{ key: pattern, "other key": pattern }`
+fn parse_fields(input: ParseStream) -> syn::Result<BTreeMap<String, PredicateAst>> {
+    let content;
+    braced!(content in input);
+    let mut fields = BTreeMap::new();
+    while !content.is_empty() {
+        let span = content.span();
+        let key = parse_key(&content)?;
+        content.parse::<Token![:]>()?;
+        if fields
+            .insert(key.clone(), parse_alternatives(&content)?)
+            .is_some()
+        {
+            return Err(Error::new(span, format!("duplicate field `{}`", key)));
+        }
+        if content.is_empty() {
+            break;
+        }
+        content.parse::<Token![,]>()?;
+    }
+    Ok(fields)
+}
+
+// `[pattern, pattern, ..rest]`
+fn parse_tuple(input: ParseStream) -> syn::Result<PredicateAst> {
+    let content;
+    bracketed!(content in input);
+    let mut items = Vec::new();
+    let mut rest = None;
+    while !content.is_empty() {
+        if content.peek(Token![..]) {
+            content.parse::<Token![..]>()?;
+            rest = Some(Box::new(parse_alternatives(&content)?));
+            end_of_arguments(&content)?;
+            break;
+        }
+        items.push(parse_alternatives(&content)?);
+        if content.is_empty() {
+            break;
+        }
+        content.parse::<Token![,]>()?;
+    }
+    Ok(PredicateAst::Tuple { items, rest })
+}
+
+// A JSON value: literals, `null`, `[...]` arrays and `{ key: value }` objects.
+fn parse_value(input: ParseStream) -> syn::Result<Value> {
+    if input.peek(token::Bracket) {
+        let content;
+        bracketed!(content in input);
+        return Ok(Value::Array(separated(&content, parse_value)?));
+    }
+    if input.peek(token::Brace) {
+        let content;
+        braced!(content in input);
+        let entries = separated(&content, |content| {
+            let key = parse_key(content)?;
+            content.parse::<Token![:]>()?;
+            Ok((key, parse_value(content)?))
+        })?;
+        return Ok(Value::Object(entries.into_iter().collect::<Map<_, _>>()));
+    }
+    if input.peek(Token![-]) || input.peek(LitInt) || input.peek(syn::LitFloat) {
+        let number = parse_number(input)?;
+        return Ok(match number {
+            _ if number.fract() == 0.0 && number.abs() < 9_007_199_254_740_992.0 => {
+                Value::from(number as i64)
+            }
+            _ => Value::from(number),
+        });
+    }
+    if input.peek(Ident) {
+        let name: Ident = input.parse()?;
+        return if name == "null" {
+            Ok(Value::Null)
+        } else {
+            Err(Error::new(name.span(), "expected a value"))
+        };
+    }
+    match input.parse::<Lit>()? {
+        Lit::Str(value) => Ok(Value::String(value.value())),
+        Lit::Bool(value) => Ok(Value::Bool(value.value)),
+        other => Err(Error::new(other.span(), "expected a value")),
+    }
+}
+
+fn parse_number(input: ParseStream) -> syn::Result<f64> {
+    let negative = input.parse::<Option<Token![-]>>()?.is_some();
+    let span = input.span();
+    let number = match input.parse::<Lit>()? {
+        Lit::Int(value) => value.base10_parse::<f64>()?,
+        Lit::Float(value) => value.base10_parse::<f64>()?,
+        _ => return Err(Error::new(span, "expected a number")),
+    };
+    if !number.is_finite() {
+        return Err(Error::new(span, "number is out of range"));
+    }
+    Ok(if negative { -number } else { number })
+}
+
+fn parse_string(input: ParseStream) -> syn::Result<String> {
+    Ok(input.parse::<LitStr>()?.value())
+}
+
+// Keys may be identifiers, including keywords such as `type`, or string literals.
+fn parse_key(input: ParseStream) -> syn::Result<String> {
+    if input.peek(LitStr) {
+        parse_string(input)
+    } else {
+        Ok(Ident::parse_any(input)?.unraw().to_string())
+    }
+}
+
+fn separated<T>(
+    input: ParseStream,
+    parser: impl Fn(ParseStream) -> syn::Result<T>,
+) -> syn::Result<Vec<T>> {
+    let mut items = Vec::new();
+    while !input.is_empty() {
+        items.push(parser(input)?);
+        if input.is_empty() {
+            break;
+        }
+        input.parse::<Token![,]>()?;
+    }
+    Ok(items)
+}
+
+// A parenthesized, comma-separated argument list.
+fn arguments<T>(
+    input: ParseStream,
+    parser: impl Fn(ParseStream) -> syn::Result<T>,
+) -> syn::Result<Vec<T>> {
+    let content;
+    parenthesized!(content in input);
+    separated(&content, parser)
+}
+
+fn single<T>(input: ParseStream, parser: impl Fn(ParseStream) -> syn::Result<T>) -> syn::Result<T> {
+    let content;
+    parenthesized!(content in input);
+    let item = parser(&content)?;
+    end_of_arguments(&content)?;
+    Ok(item)
+}
+
+fn end_of_arguments(input: ParseStream) -> syn::Result<()> {
+    input.parse::<Option<Token![,]>>()?;
+    if input.is_empty() {
+        Ok(())
+    } else {
+        Err(input.error("unexpected argument"))
+    }
+}
+
+fn predicate_tokens(predicate: &PredicateAst) -> TokenStream {
+    let ast = quote!(::match_pattern_rs::PredicateAst);
+    match predicate {
+        PredicateAst::IsNumber => quote!(#ast::IsNumber),
+        PredicateAst::IsString => quote!(#ast::IsString),
+        PredicateAst::IsBoolean => quote!(#ast::IsBoolean),
+        PredicateAst::IsNull => quote!(#ast::IsNull),
+        PredicateAst::IsUndefined => quote!(#ast::IsUndefined),
+        PredicateAst::Eq { value } => {
+            let value = value_tokens(value);
+            quote!(#ast::Eq { value: #value })
+        }
+        PredicateAst::Tag { key, value } => {
+            let key = string_tokens(key);
+            let value = value_tokens(value);
+            quote!(#ast::Tag { key: #key, value: #value })
+        }
+        PredicateAst::OneOf { values } => {
+            let values = values.iter().map(value_tokens);
+            quote!(#ast::OneOf { values: ::std::vec![#(#values),*] })
+        }
+        PredicateAst::StartsWith { value } => {
+            let value = string_tokens(value);
+            quote!(#ast::StartsWith { value: #value })
+        }
+        PredicateAst::EndsWith { value } => {
+            let value = string_tokens(value);
+            quote!(#ast::EndsWith { value: #value })
+        }
+        PredicateAst::Contains { value } => {
+            let value = string_tokens(value);
+            quote!(#ast::Contains { value: #value })
+        }
+        PredicateAst::Regex { pattern, flags } => {
+            let pattern = string_tokens(pattern);
+            let flags = option_tokens(flags.as_deref().map(string_tokens));
+            quote!(#ast::Regex { pattern: #pattern, flags: #flags })
+        }
+        PredicateAst::Range {
+            min,
+            max,
+            min_inclusive,
+            max_inclusive,
+        } => {
+            let min = option_tokens(min.map(Literal::f64_suffixed).map(|min| quote!(#min)));
+            let max = option_tokens(max.map(Literal::f64_suffixed).map(|max| quote!(#max)));
+            quote! {
+                #ast::Range {
+                    min: #min,
+                    max: #max,
+                    min_inclusive: #min_inclusive,
+                    max_inclusive: #max_inclusive,
+                }
+            }
+        }
+        PredicateAst::Shape { fields, exact } => {
+            let fields = fields.iter().map(|(key, node)| {
+                let key = string_tokens(key);
+                let node = predicate_tokens(node);
+                quote!((#key, #node))
+            });
+            quote! {
+                #ast::Shape {
+                    fields: ::std::collections::BTreeMap::from([#(#fields),*]),
+                    exact: #exact,
+                }
+            }
+        }
+        PredicateAst::Tuple { items, rest } => {
+            let items = items.iter().map(predicate_tokens);
+            let rest = option_tokens(rest.as_deref().map(boxed_tokens));
+            quote!(#ast::Tuple { items: ::std::vec![#(#items),*], rest: #rest })
+        }
+        PredicateAst::ArrayOf { predicate } => {
+            let predicate = boxed_tokens(predicate);
+            quote!(#ast::ArrayOf { predicate: #predicate })
+        }
+        PredicateAst::ArrayLength { length } => {
+            let length = Literal::usize_unsuffixed(*length);
+            quote!(#ast::ArrayLength { length: #length })
+        }
+        PredicateAst::And { predicates } => {
+            let predicates = predicates.iter().map(predicate_tokens);
+            quote!(#ast::And { predicates: ::std::vec![#(#predicates),*] })
+        }
+        PredicateAst::Or { predicates } => {
+            let predicates = predicates.iter().map(predicate_tokens);
+            quote!(#ast::Or { predicates: ::std::vec![#(#predicates),*] })
+        }
+        PredicateAst::Not { predicate } => {
+            let predicate = boxed_tokens(predicate);
+            quote!(#ast::Not { predicate: #predicate })
+        }
+        PredicateAst::Slot { slot } => {
+            let slot = Literal::u32_unsuffixed(*slot);
+            quote!(#ast::Slot { slot: #slot })
+        }
+        PredicateAst::Bind { name, predicate } => {
+            let name = string_tokens(name);
+            let predicate = boxed_tokens(predicate);
+            quote!(#ast::Bind { name: #name, predicate: #predicate })
+        }
+        PredicateAst::Wildcard => quote!(#ast::Wildcard),
+    }
+}
+
+fn value_tokens(value: &Value) -> TokenStream {
+    let json = quote!(::match_pattern_rs::__private::serde_json);
+    match value {
+        Value::Null => quote!(#json::Value::Null),
+        Value::Bool(value) => quote!(#json::Value::Bool(#value)),
+        Value::Number(number) => {
+            let literal = match (number.as_i64(), number.as_f64()) {
+                (Some(integer), _) => Literal::i64_suffixed(integer),
+                (None, Some(float)) => Literal::f64_suffixed(float),
+                (None, None) => unreachable!("numbers parsed from literals are finite"),
+            };
+            quote!(#json::Value::from(#literal))
+        }
+        Value::String(value) => {
+            let value = string_tokens(value);
+            quote!(#json::Value::String(#value))
+        }
+        Value::Array(items) => {
+            let items = items.iter().map(value_tokens);
+            quote!(#json::Value::Array(::std::vec![#(#items),*]))
+        }
+        Value::Object(map) => {
+            let entries = map.iter().map(|(key, item)| {
+                let key = string_tokens(key);
+                let item = value_tokens(item);
+                quote!((#key, #item))
+            });
+            quote! {
+                #json::Value::Object(::core::iter::FromIterator::from_iter([#(#entries),*]))
+            }
+        }
+    }
+}
+
+fn string_tokens(value: &str) -> TokenStream {
+    quote!(::std::string::String::from(#value))
+}
+
+fn boxed_tokens(predicate: &PredicateAst) -> TokenStream {
+    let predicate = predicate_tokens(predicate);
+    quote!(::std::boxed::Box::new(#predicate))
+}
+
+fn option_tokens(value: Option<TokenStream>) -> TokenStream {
+    match value {
+        Some(value) => quote!(::core::option::Option::Some(#value)),
+        None => quote!(::core::option::Option::None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(input: TokenStream) -> MatchProgram {
+        syn::parse2::<Program>(input).unwrap().program
+    }
+
+    #[test]
+    fn test_parses_patterns() {
+        let program = parse(quote! {
+            tag("type", "pay") | tag("type", "refund") => 0,
+            shape { amount: gt(0), type: one_of("a", -1.5) } => 1,
+            [is_string, ..amount @ gte(0)] => 2,
+            _ => 3,
+        });
+
+        assert_eq!(
+            program.branches[0].predicate,
+            PredicateAst::Or {
+                predicates: vec![
+                    PredicateAst::Tag {
+                        key: "type".to_string(),
+                        value: json!("pay"),
+                    },
+                    PredicateAst::Tag {
+                        key: "type".to_string(),
+                        value: json!("refund"),
+                    },
+                ],
+            }
+        );
+        assert_eq!(
+            program.branches[1].predicate,
+            PredicateAst::Shape {
+                fields: BTreeMap::from([
+                    (
+                        "amount".to_string(),
+                        PredicateAst::Range {
+                            min: Some(0.0),
+                            max: None,
+                            min_inclusive: false,
+                            max_inclusive: false,
+                        },
+                    ),
+                    (
+                        "type".to_string(),
+                        PredicateAst::OneOf {
+                            values: vec![json!("a"), json!(-1.5)],
+                        },
+                    ),
+                ]),
+                exact: false,
+            }
+        );
+        assert_eq!(
+            program.branches[2].predicate,
+            PredicateAst::Tuple {
+                items: vec![PredicateAst::IsString],
+                rest: Some(Box::new(PredicateAst::Bind {
+                    name: "amount".to_string(),
+                    predicate: Box::new(PredicateAst::Range {
+                        min: Some(0.0),
+                        max: None,
+                        min_inclusive: true,
+                        max_inclusive: false,
+                    }),
+                })),
+            }
+        );
+        assert_eq!(program.branches[3].action_index, Some(3));
+    }
+
+    #[test]
+    fn test_unreachable_branch_is_an_error() {
+        let error = expand(quote! {
+            tag("type", "pay") => 0,
+            _ => 1,
+            tag("type", "refund") => 2,
+        })
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "branch 2 is shadowed by a previous predicate"
+        );
+
+        let allowed = parse(quote! {
+            _ => 1,
+            #[allow(unreachable_branch)]
+            tag("type", "refund") => 2,
+        });
+        assert_eq!(allowed.branches[1].allow, ["unreachable_branch"]);
+        assert!(
+            expand(quote! {
+                _ => 1,
+                #[allow(unreachable_branch)]
+                tag("type", "refund") => 2,
+            })
+            .is_ok()
+        );
+
+        let error = expand(quote!(matches("x") => 0)).unwrap_err();
+        assert_eq!(error.to_string(), "unknown pattern `matches`");
+    }
+}
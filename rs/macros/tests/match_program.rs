@@ -0,0 +1,86 @@
+use match_pattern_macros::match_program;
+use match_pattern_rs::{BranchAst, MatchProgram, PredicateAst, compile_program, evaluate_plan};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+#[test]
+fn test_expands_to_match_program() {
+    let program = match_program! {
+        tag("type", "pay") => 0,
+        shape { amount: gt(0), "currency code": eq({ code: "EUR", digits: [2, null] }) } => 1,
+        _ => 2,
+    };
+
+    assert_eq!(
+        program,
+        MatchProgram {
+            branches: vec![
+                BranchAst {
+                    action_index: Some(0),
+                    predicate: PredicateAst::Tag {
+                        key: "type".to_string(),
+                        value: json!("pay"),
+                    },
+                    allow: Vec::new(),
+                },
+                BranchAst {
+                    action_index: Some(1),
+                    predicate: PredicateAst::Shape {
+                        fields: BTreeMap::from([
+                            (
+                                "amount".to_string(),
+                                PredicateAst::Range {
+                                    min: Some(0.0),
+                                    max: None,
+                                    min_inclusive: false,
+                                    max_inclusive: false,
+                                },
+                            ),
+                            (
+                                "currency code".to_string(),
+                                PredicateAst::Eq {
+                                    value: json!({ "code": "EUR", "digits": [2, null] }),
+                                },
+                            ),
+                        ]),
+                        exact: false,
+                    },
+                    allow: Vec::new(),
+                },
+                BranchAst {
+                    action_index: Some(2),
+                    predicate: PredicateAst::Wildcard,
+                    allow: Vec::new(),
+                },
+            ],
+            input_schema: None,
+        }
+    );
+}
+
+#[test]
+fn test_expanded_program_compiles_and_evaluates() {
+    let plan = compile_program(&match_program! {
+        exact { type: "refund", lines: array_of(starts_with("SKU-")) } => 0,
+        [is_number, ..slot(0)] => 1,
+        not(is_null) => 2,
+    });
+    let positive = |_: u32, value: Option<&serde_json::Value>| {
+        value
+            .and_then(|value| value.as_f64())
+            .is_some_and(|number| number > 0.0)
+    };
+
+    assert_eq!(plan.dynamic_slot_count, 1);
+    let refund = json!({ "type": "refund", "lines": ["SKU-1"] });
+    assert_eq!(evaluate_plan(&plan, Some(&refund), positive), Some(0));
+    assert_eq!(
+        evaluate_plan(&plan, Some(&json!([0, 1, 2])), positive),
+        Some(1)
+    );
+    assert_eq!(
+        evaluate_plan(&plan, Some(&json!([0, -1])), positive),
+        Some(2)
+    );
+    assert_eq!(evaluate_plan(&plan, Some(&json!(null)), positive), None);
+}
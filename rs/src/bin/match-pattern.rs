@@ -0,0 +1,367 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use match_pattern_rs::{
+    CompileDiagnostic, CompileOptions, MatchProgram, Severity, compile_program_with_options,
+    encode_plan, parse_program_json, parse_program_text,
+};
+
+const USAGE: &str = "usage: match-pattern [options] <program.json | ->
+
+Compiles a MatchProgram (or a bare branch array) and writes the CompilePlan.
+Diagnostics go to stderr. An error-level diagnostic fails the compilation.
+
+options:
+  -o, --output <file>     write the plan to <file> instead of stdout
+      --check             report diagnostics only, do not write the plan
+      --text              read the program in the text syntax instead of JSON
+      --binary            write the plan in the binary encoding instead of JSON
+      --format <format>   diagnostic format: human (default) or json
+      --deny-warnings     exit with status 1 when a warning is reported
+      --severity <c>=<l>  report code <c> at level <l>: off, info, warning or error
+      --decision-tree     build the decision tree
+      --order-by-cost     reorder and/or operands by estimated cost
+      --dispatch          build the tag dispatch table
+  -h, --help              print this help
+";
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    input: String,
+    output: Option<String>,
+    check: bool,
+    text: bool,
+    binary: bool,
+    json: bool,
+    deny_warnings: bool,
+    options: CompileOptions,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprint!("match-pattern: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&args, &mut io::stdout(), &mut io::stderr()) {
+        Ok(status) => ExitCode::from(status),
+        Err(message) => {
+            eprintln!("match-pattern: {}", message);
+            ExitCode::from(2)
+        }
+    }
+}
+
+// `Ok(None)` asks for the usage text.
+fn parse_args(mut arguments: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut args = Args::default();
+    let mut input = None;
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => {
+                args.output = Some(
+                    arguments
+                        .next()
+                        .ok_or_else(|| format!("{} needs a file", argument))?,
+                );
+            }
+            "--check" => args.check = true,
+            "--text" => args.text = true,
+            "--binary" => args.binary = true,
+            "--format" => match arguments.next().as_deref() {
+                Some("human") => args.json = false,
+                Some("json") => args.json = true,
+                _ => return Err("--format must be human or json".to_string()),
+            },
+            "--deny-warnings" => args.deny_warnings = true,
+            "--severity" => {
+                let setting = arguments.next().unwrap_or_default();
+                let (code, level) = setting
+                    .split_once('=')
+                    .ok_or_else(|| "--severity needs <code>=<level>".to_string())?;
+                let level = serde_json::from_value::<Severity>(level.into())
+                    .map_err(|_| format!("unknown severity {}", level))?;
+                args.options.severity.insert(code.to_string(), level);
+            }
+            "--decision-tree" => args.options.decision_tree = true,
+            "--order-by-cost" => args.options.order_by_cost = true,
+            "--dispatch" => args.options.dispatch = true,
+            flag if flag.starts_with('-') && flag != "-" => {
+                return Err(format!("unknown option {}", flag));
+            }
+            _ if input.is_some() => return Err("expected a single input file".to_string()),
+            _ => input = Some(argument),
+        }
+    }
+
+    args.input = input.ok_or_else(|| "missing input file".to_string())?;
+    Ok(Some(args))
+}
+
+// Returns the exit status: 1 when an error was reported, or a warning with `--deny-warnings`.
+// The plan is not written when there is an error.
+fn run(args: &Args, stdout: &mut impl Write, stderr: &mut impl Write) -> Result<u8, String> {
+    let source = if args.input == "-" {
+        let mut source = String::new();
+        io::stdin()
+            .read_to_string(&mut source)
+            .map_err(|err| format!("failed to read stdin: {}", err))?;
+        source
+    } else {
+        fs::read_to_string(&args.input)
+            .map_err(|err| format!("failed to read {}: {}", args.input, err))?
+    };
+    let program = if args.text {
+        parse_program_text(&source)
+            .map_err(|err| format!("{}:{}", display_name(&args.input), err))?
+    } else {
+        parse_program(&source)?
+    };
+    let plan = compile_program_with_options(&program, &args.options);
+    let failed = plan
+        .diagnostics
+        .iter()
+        .any(|item| item.level == Severity::Error);
+
+    if !args.check && !failed {
+        let bytes = if args.binary {
+            encode_plan(&plan)
+        } else {
+            let json = serde_json::to_string_pretty(&plan)
+                .map_err(|err| format!("failed to serialize compile plan: {}", err))?;
+            (json + "\n").into_bytes()
+        };
+        match &args.output {
+            Some(path) => fs::write(path, bytes)
+                .map_err(|err| format!("failed to write {}: {}", path, err))?,
+            None => stdout
+                .write_all(&bytes)
+                .map_err(|err| format!("failed to write the plan: {}", err))?,
+        }
+    }
+
+    let report = if args.json {
+        json_report(&plan.diagnostics)?
+    } else {
+        human_report(&args.input, &plan.diagnostics)
+    };
+    stderr
+        .write_all(report.as_bytes())
+        .map_err(|err| format!("failed to write diagnostics: {}", err))?;
+
+    let denied = args.deny_warnings
+        && plan
+            .diagnostics
+            .iter()
+            .any(|item| item.level == Severity::Warning);
+    Ok(if failed || denied { 1 } else { 0 })
+}
+
+// Names the path into the program, which serde's message leaves out.
+fn parse_program(source: &str) -> Result<MatchProgram, String> {
+    parse_program_json(source).map_err(|err| match err.path().as_slice() {
+        [] => err.to_string(),
+        path => format!("{} (at {})", err, path.join(".")),
+    })
+}
+
+fn human_report(input: &str, diagnostics: &[CompileDiagnostic]) -> String {
+    let name = display_name(input);
+    diagnostics
+        .iter()
+        .map(|item| {
+            let mut line = format!(
+                "{}: {}[{}]: {}\n",
+                name, item.level, item.code, item.message
+            );
+            if let (Some(index), Some(path)) = (item.branch_index, &item.path) {
+                line.push_str(&format!("  at: {}\n", predicate_path(index, path)));
+            }
+            if let Some(counterexample) = &item.counterexample {
+                line.push_str(&format!("  counterexample: {}\n", counterexample));
+            } else if item.counterexample_undefined {
+                line.push_str("  counterexample: undefined\n");
+            }
+            for related in &item.related {
+                line.push_str(&format!(
+                    "  related: {}: {}\n",
+                    predicate_path(related.branch_index, &related.path),
+                    related.message
+                ));
+            }
+            line
+        })
+        .collect()
+}
+
+// Spells a diagnostic path from the program root, like the parse errors do.
+fn predicate_path(branch_index: usize, path: &[String]) -> String {
+    let mut segments = vec![
+        "branches".to_string(),
+        branch_index.to_string(),
+        "predicate".to_string(),
+    ];
+    segments.extend_from_slice(path);
+    segments.join(".")
+}
+
+fn display_name(input: &str) -> &str {
+    if input == "-" { "<stdin>" } else { input }
+}
+
+fn json_report(diagnostics: &[CompileDiagnostic]) -> Result<String, String> {
+    serde_json::to_string(diagnostics)
+        .map(|json| json + "\n")
+        .map_err(|err| format!("failed to serialize diagnostics: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn args(arguments: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(arguments.iter().map(|argument| argument.to_string()))
+    }
+
+    fn write_program(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "match-pattern-{}-{}.json",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            args(&[
+                "rules.json",
+                "--deny-warnings",
+                "--format",
+                "json",
+                "--dispatch"
+            ]),
+            Ok(Some(Args {
+                input: "rules.json".to_string(),
+                json: true,
+                deny_warnings: true,
+                options: CompileOptions {
+                    dispatch: true,
+                    ..CompileOptions::default()
+                },
+                ..Args::default()
+            }))
+        );
+        assert_eq!(args(&["-", "--help"]), Ok(None));
+        assert!(args(&[]).is_err());
+        assert!(args(&["a.json", "b.json"]).is_err());
+        assert!(args(&["--format", "xml", "a.json"]).is_err());
+        assert!(args(&["--severity", "dynamic_slot", "a.json"]).is_err());
+        assert!(args(&["--severity", "dynamic_slot=fatal", "a.json"]).is_err());
+    }
+
+    #[test]
+    fn test_branch_array_with_deny_warnings() {
+        let input = write_program(
+            "shadowed",
+            &json!([
+                { "actionIndex": 0, "predicate": { "kind": "wildcard" } },
+                { "actionIndex": 1, "predicate": { "kind": "isNumber" } }
+            ])
+            .to_string(),
+        );
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut args = args(&[&input, "--deny-warnings"]).unwrap().unwrap();
+
+        assert_eq!(run(&args, &mut stdout, &mut stderr), Ok(1));
+        let plan: serde_json::Value = serde_json::from_slice(&stdout).unwrap();
+        assert_eq!(plan["branches"].as_array().map(Vec::len), Some(2));
+        assert_eq!(
+            String::from_utf8(stderr).unwrap(),
+            format!(
+                "{}: warning[unreachable_branch]: branch 1 is shadowed by a previous predicate\n  at: branches.1.predicate\n  related: branches.0.predicate: branch 0 already matches every input this branch matches\n",
+                input
+            )
+        );
+
+        args.deny_warnings = false;
+        args.check = true;
+        args.json = true;
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        assert_eq!(run(&args, &mut stdout, &mut stderr), Ok(0));
+        assert!(stdout.is_empty());
+        let diagnostics: serde_json::Value = serde_json::from_slice(&stderr).unwrap();
+        assert_eq!(diagnostics[0]["code"], json!("unreachable_branch"));
+
+        let mut strict = tests::args(&[&input, "--severity", "unreachable_branch=error"])
+            .unwrap()
+            .unwrap();
+        let mut stdout = Vec::new();
+        assert_eq!(run(&strict, &mut stdout, &mut Vec::new()), Ok(1));
+        assert!(stdout.is_empty());
+        strict
+            .options
+            .severity
+            .insert("unreachable_branch".to_string(), Severity::Off);
+        assert_eq!(run(&strict, &mut Vec::new(), &mut Vec::new()), Ok(0));
+        fs::remove_file(input).unwrap();
+    }
+
+    #[test]
+    fn test_text_program() {
+        let input = write_program("text", "{ type: \"pay\" } => 0\n_ => 1\n");
+        let mut args = args(&[&input, "--text", "--check", "--deny-warnings"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(run(&args, &mut Vec::new(), &mut Vec::new()), Ok(0));
+        fs::remove_file(&input).unwrap();
+
+        args.input = write_program("invalid", "{ type: \"pay\" } => 0\n{ amount: > }\n");
+        assert_eq!(
+            run(&args, &mut Vec::new(), &mut Vec::new()),
+            Err(format!(
+                "{}:2:13: expected a number, found `}}`",
+                args.input
+            ))
+        );
+        fs::remove_file(&args.input).unwrap();
+    }
+
+    #[test]
+    fn test_binary_output() {
+        let input = write_program("binary", "string => 0\n");
+        let args = args(&[&input, "--text", "--binary"]).unwrap().unwrap();
+        let mut stdout = Vec::new();
+        assert_eq!(run(&args, &mut stdout, &mut Vec::new()), Ok(0));
+        let plan = match_pattern_rs::decode_plan(&stdout).unwrap();
+        assert_eq!(plan.branches.len(), 1);
+        fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn test_program_errors_name_the_path() {
+        assert_eq!(
+            parse_program(r#"{ "branches": [{ "actionIndex": "0", "predicate": { "kind": "wildcard" } }] }"#),
+            Err("invalid compile program: invalid type: string \"0\", expected usize at line 1 column 35 (at branches.0.actionIndex)".to_string())
+        );
+        assert!(
+            parse_program(r#"[{ "predicate": { "kind": "isNull" } }]"#)
+                .is_ok_and(|program| program.branches.len() == 1)
+        );
+        assert!(parse_program("[] []").is_err());
+    }
+}
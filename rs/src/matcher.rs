@@ -1,9 +1,10 @@
 use js_sys::{Array, Function, Object, Reflect};
+use match_pattern_core::MatchPatternError;
 use wasm_bindgen::prelude::*;
 
 use crate::types::Pattern;
 
-pub fn check_pattern(data: &JsValue, pattern: &Pattern) -> Result<bool, JsValue> {
+pub fn check_pattern(data: &JsValue, pattern: &Pattern) -> Result<bool, MatchPatternError> {
     match pattern {
         Pattern::Value(pattern_value) => check_value_equal(data, pattern_value),
         Pattern::Object(pattern_obj) => check_object_match(data, pattern_obj),
@@ -12,7 +13,7 @@ pub fn check_pattern(data: &JsValue, pattern: &Pattern) -> Result<bool, JsValue>
     }
 }
 
-pub fn check_value_equal(a: &JsValue, b: &JsValue) -> Result<bool, JsValue> {
+pub fn check_value_equal(a: &JsValue, b: &JsValue) -> Result<bool, MatchPatternError> {
     if a.is_null() && b.is_null() {
         return Ok(true);
     }
@@ -37,7 +38,7 @@ pub fn check_value_equal(a: &JsValue, b: &JsValue) -> Result<bool, JsValue> {
     Ok(false)
 }
 
-pub fn check_array_equal(a: &JsValue, b: &JsValue) -> Result<bool, JsValue> {
+pub fn check_array_equal(a: &JsValue, b: &JsValue) -> Result<bool, MatchPatternError> {
     let arr_a = Array::from(a);
     let arr_b = Array::from(b);
 
@@ -48,15 +49,16 @@ pub fn check_array_equal(a: &JsValue, b: &JsValue) -> Result<bool, JsValue> {
     arr_a
         .iter()
         .zip(arr_b.iter())
-        .find_map(|(a, b)| match check_value_equal(&a, &b) {
+        .enumerate()
+        .find_map(|(index, (a, b))| match check_value_equal(&a, &b) {
             Ok(true) => None,
             Ok(false) => Some(Ok(false)),
-            Err(e) => Some(Err(e)),
+            Err(e) => Some(Err(e.within(index.to_string()))),
         })
         .unwrap_or(Ok(true))
 }
 
-pub fn check_object_equal(a: &JsValue, b: &JsValue) -> Result<bool, JsValue> {
+pub fn check_object_equal(a: &JsValue, b: &JsValue) -> Result<bool, MatchPatternError> {
     let obj_a = Object::from(a.clone());
     let obj_b = Object::from(b.clone());
 
@@ -72,27 +74,27 @@ pub fn check_object_equal(a: &JsValue, b: &JsValue) -> Result<bool, JsValue> {
         .find_map(|key| {
             match Reflect::has(&obj_b, &key) {
                 Ok(false) => return Some(Ok(false)),
-                Err(e) => return Some(Err(e)),
+                Err(e) => return Some(Err(thrown_at(&key, &e))),
                 Ok(true) => {}
             }
             let val_a = match Reflect::get(&obj_a, &key) {
                 Ok(v) => v,
-                Err(e) => return Some(Err(e)),
+                Err(e) => return Some(Err(thrown_at(&key, &e))),
             };
             let val_b = match Reflect::get(&obj_b, &key) {
                 Ok(v) => v,
-                Err(e) => return Some(Err(e)),
+                Err(e) => return Some(Err(thrown_at(&key, &e))),
             };
             match check_value_equal(&val_a, &val_b) {
                 Ok(true) => None,
                 Ok(false) => Some(Ok(false)),
-                Err(e) => Some(Err(e)),
+                Err(e) => Some(Err(e.within(key_name(&key)))),
             }
         })
         .unwrap_or(Ok(true))
 }
 
-pub fn check_object_match(data: &JsValue, pattern: &JsValue) -> Result<bool, JsValue> {
+pub fn check_object_match(data: &JsValue, pattern: &JsValue) -> Result<bool, MatchPatternError> {
     if !pattern.is_object() {
         return check_value_equal(data, pattern);
     }
@@ -106,29 +108,40 @@ pub fn check_object_match(data: &JsValue, pattern: &JsValue) -> Result<bool, JsV
         .find_map(|key| {
             match Reflect::has(&data_obj, &key) {
                 Ok(false) => return Some(Ok(false)),
-                Err(e) => return Some(Err(e)),
+                Err(e) => return Some(Err(thrown_at(&key, &e))),
                 Ok(true) => {}
             }
             let data_value = match Reflect::get(&data_obj, &key) {
                 Ok(v) => v,
-                Err(e) => return Some(Err(e)),
+                Err(e) => return Some(Err(thrown_at(&key, &e))),
             };
             let pattern_value = match Reflect::get(&pattern_obj, &key) {
                 Ok(v) => v,
-                Err(e) => return Some(Err(e)),
+                Err(e) => return Some(Err(thrown_at(&key, &e))),
             };
             match check_object_match(&data_value, &pattern_value) {
                 Ok(true) => None,
                 Ok(false) => Some(Ok(false)),
-                Err(e) => Some(Err(e)),
+                Err(e) => Some(Err(e.within(key_name(&key)))),
             }
         })
         .unwrap_or(Ok(true))
 }
 
-fn check_function_match(data: &JsValue, func: &JsValue) -> Result<bool, JsValue> {
+fn check_function_match(data: &JsValue, func: &JsValue) -> Result<bool, MatchPatternError> {
     let function = Function::from(func.clone());
-    let result = function.call1(&JsValue::NULL, data)?;
+    let result = function
+        .call1(&JsValue::NULL, data)
+        .map_err(|err| MatchPatternError::thrown(Vec::new(), &err))?;
 
     Ok(result.is_truthy())
 }
+
+// `Object.keys` only yields strings.
+fn key_name(key: &JsValue) -> String {
+    key.as_string().unwrap_or_default()
+}
+
+fn thrown_at(key: &JsValue, exception: &JsValue) -> MatchPatternError {
+    MatchPatternError::thrown(vec![key_name(key)], exception)
+}
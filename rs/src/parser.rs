@@ -1,44 +1,55 @@
 use js_sys::{Array, Reflect};
+use match_pattern_core::MatchPatternError;
 use wasm_bindgen::prelude::*;
 
 use crate::types::Pattern;
 
-pub fn parse_patterns(patterns_js: &JsValue) -> Result<Vec<Pattern>, JsValue> {
+pub fn parse_patterns(patterns_js: &JsValue) -> Result<Vec<Pattern>, MatchPatternError> {
     if !Array::is_array(patterns_js) {
-        return Err(JsValue::from_str("patterns must be an array"));
+        return Err(MatchPatternError::PatternsNotArray { path: Vec::new() });
     }
 
     Array::from(patterns_js)
         .iter()
-        .map(|item| parse_pattern(&item))
+        .enumerate()
+        .map(|(index, item)| parse_pattern(&item).map_err(|err| err.within(index.to_string())))
         .collect()
 }
 
-pub fn parse_pattern(pattern_js: &JsValue) -> Result<Pattern, JsValue> {
-    let pattern_type = Reflect::get(pattern_js, &JsValue::from_str("type"))
-        .map_err(|_| JsValue::from_str("Missing 'type' field"))?;
+pub fn parse_pattern(pattern_js: &JsValue) -> Result<Pattern, MatchPatternError> {
+    if !pattern_js.is_object() {
+        return Err(MatchPatternError::PatternNotObject { path: Vec::new() });
+    }
+    let pattern_type = field(pattern_js, "type")?;
+    if pattern_type.is_undefined() {
+        return Err(MatchPatternError::MissingField {
+            path: Vec::new(),
+            field: "type".to_string(),
+        });
+    }
 
     let type_str = pattern_type
         .as_string()
-        .ok_or_else(|| JsValue::from_str("'type' must be a string"))?;
+        .ok_or_else(|| MatchPatternError::InvalidField {
+            path: vec!["type".to_string()],
+            field: "type".to_string(),
+            expected: "a string".to_string(),
+        })?;
 
     match type_str.as_str() {
-        "Value" => {
-            let value = Reflect::get(pattern_js, &JsValue::from_str("value"))?;
-            Ok(Pattern::Value(value))
-        }
-        "Object" => {
-            let obj = Reflect::get(pattern_js, &JsValue::from_str("pattern"))?;
-            Ok(Pattern::Object(obj))
-        }
-        "Function" => {
-            let func_value = Reflect::get(pattern_js, &JsValue::from_str("func"))?;
-            Ok(Pattern::Function(func_value))
-        }
+        "Value" => Ok(Pattern::Value(field(pattern_js, "value")?)),
+        "Object" => Ok(Pattern::Object(field(pattern_js, "pattern")?)),
+        "Function" => Ok(Pattern::Function(field(pattern_js, "func")?)),
         "Wildcard" => Ok(Pattern::Wildcard),
-        _ => Err(JsValue::from_str(&format!(
-            "Unknown pattern type: {}",
-            type_str
-        ))),
+        _ => Err(MatchPatternError::UnknownPatternType {
+            path: vec!["type".to_string()],
+            found: type_str,
+        }),
     }
 }
+
+// On an object `Reflect.get` only throws from a proxy trap or getter.
+fn field(pattern_js: &JsValue, name: &str) -> Result<JsValue, MatchPatternError> {
+    Reflect::get(pattern_js, &JsValue::from_str(name))
+        .map_err(|err| MatchPatternError::thrown(vec![name.to_string()], &err))
+}
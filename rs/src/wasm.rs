@@ -0,0 +1,381 @@
+use js_sys::{Object, Reflect};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use match_pattern_core::{
+    BranchAst, CompileOptions, CompilePlan, MatchPatternError, MatchProgram, PlanLoadError,
+    decode_plan, encode_plan, explain_plan, from_value, generate_js, load_plan,
+    try_compile_program, upgrade_plan,
+};
+
+use crate::matcher::check_pattern;
+use crate::parser::parse_patterns;
+use crate::utils::log;
+
+#[wasm_bindgen]
+pub fn match_pattern(data: JsValue, patterns: JsValue) -> Result<JsValue, MatchPatternError> {
+    let patterns_vec = parse_patterns(&patterns)?;
+
+    for (index, pattern) in patterns_vec.iter().enumerate() {
+        log(&format!("检查分支 {}", index));
+
+        if check_pattern(&data, pattern).map_err(|err| err.within(index.to_string()))? {
+            log(&format!("✓ 分支 {} 匹配！", index));
+            return Ok(JsValue::from_f64(index as f64));
+        }
+    }
+
+    log("没有匹配的分支");
+    Ok(JsValue::UNDEFINED)
+}
+
+#[wasm_bindgen]
+pub fn compile_match_plan(program: JsValue) -> Result<JsValue, MatchPatternError> {
+    compile_match_plan_with_options(program, JsValue::UNDEFINED)
+}
+
+#[wasm_bindgen]
+pub fn compile_match_plan_with_options(
+    program: JsValue,
+    options: JsValue,
+) -> Result<JsValue, MatchPatternError> {
+    let invalid_program = |(path, message)| MatchPatternError::InvalidProgram { path, message };
+    let program = from_js(program).map_err(invalid_program)?;
+    // A bare branch array is accepted as a program without `inputSchema`.
+    let parsed_program = if program.is_array() {
+        from_value::<Vec<BranchAst>>(program).map(|branches| MatchProgram {
+            branches,
+            input_schema: None,
+        })
+    } else {
+        from_value::<MatchProgram>(program)
+    }
+    .map_err(invalid_program)?;
+    let parsed_options = if options.is_undefined() || options.is_null() {
+        CompileOptions::default()
+    } else {
+        from_js(options)
+            .and_then(from_value::<CompileOptions>)
+            .map_err(|(path, message)| MatchPatternError::InvalidOptions { path, message })?
+    };
+
+    let plan = try_compile_program(&parsed_program, &parsed_options).map_err(|error| {
+        let path = error
+            .errors
+            .first()
+            .and_then(|item| item.branch_index)
+            .map(|index| vec!["branches".to_string(), index.to_string()])
+            .unwrap_or_default();
+        MatchPatternError::CompileFailed { path, error }
+    })?;
+    to_js(&plan)
+}
+
+fn from_js(value: JsValue) -> Result<serde_json::Value, (Vec<String>, String)> {
+    serde_wasm_bindgen::from_value(value).map_err(|err| (Vec::new(), err.to_string()))
+}
+
+fn to_js(value: &impl Serialize) -> Result<JsValue, MatchPatternError> {
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    value
+        .serialize(&serializer)
+        .map_err(|err| MatchPatternError::Serialize {
+            message: err.to_string(),
+        })
+}
+
+// Checks `version` and migrates plans written by older compilers.
+fn parse_plan(plan: JsValue) -> Result<CompilePlan, MatchPatternError> {
+    let value =
+        from_js(plan).map_err(|(path, message)| PlanLoadError::Invalid { path, message })?;
+    Ok(load_plan(value)?)
+}
+
+// Returns the plan upgraded to `COMPILE_PLAN_VERSION`; newer plans are rejected.
+#[wasm_bindgen]
+pub fn load_match_plan(plan: JsValue) -> Result<JsValue, MatchPatternError> {
+    to_js(&parse_plan(plan)?)
+}
+
+// Returns the plan in the binary encoding, as a `Uint8Array`.
+#[wasm_bindgen]
+pub fn encode_match_plan(plan: JsValue) -> Result<Vec<u8>, MatchPatternError> {
+    let parsed_plan = parse_plan(plan)?;
+    Ok(encode_plan(&parsed_plan))
+}
+
+#[wasm_bindgen]
+pub fn decode_match_plan(bytes: &[u8]) -> Result<JsValue, MatchPatternError> {
+    let plan = upgrade_plan(decode_plan(bytes)?)?;
+    to_js(&plan)
+}
+
+// Returns the source of a standalone `(value, slots) => actionIndex` function.
+#[wasm_bindgen]
+pub fn generate_js_matcher(plan: JsValue) -> Result<JsValue, MatchPatternError> {
+    let parsed_plan = parse_plan(plan)?;
+    Ok(JsValue::from_str(&generate_js(&parsed_plan)))
+}
+
+// `slots` is an optional `(slot, value) => boolean` callback; without it every slot fails.
+#[wasm_bindgen]
+pub fn explain_match_plan(
+    plan: JsValue,
+    value: JsValue,
+    slots: JsValue,
+) -> Result<JsValue, MatchPatternError> {
+    let parsed_plan = parse_plan(plan)?;
+    let parsed_value = if value.is_undefined() {
+        None
+    } else {
+        Some(
+            from_js(value)
+                .map_err(|(path, message)| MatchPatternError::InvalidValue { path, message })?,
+        )
+    };
+    let slot_fn = slots.dyn_into::<js_sys::Function>().ok();
+
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    let trace = explain_plan(&parsed_plan, parsed_value.as_ref(), |slot, input| {
+        let Some(slot_fn) = &slot_fn else {
+            return false;
+        };
+        let input = input
+            .and_then(|item| item.serialize(&serializer).ok())
+            .unwrap_or(JsValue::UNDEFINED);
+        slot_fn
+            .call2(&JsValue::NULL, &JsValue::from(slot), &input)
+            .is_ok_and(|result| result.is_truthy())
+    });
+    to_js(&trace)
+}
+
+#[wasm_bindgen]
+pub fn test_reflect() {
+    let obj = Object::new();
+    Reflect::set(
+        &obj,
+        &JsValue::from_str("name"),
+        &JsValue::from_str("Mitori"),
+    )
+    .unwrap();
+    Reflect::set(&obj, &JsValue::from_str("age"), &JsValue::from_f64(25.0)).unwrap();
+
+    let name = Reflect::get(&obj, &JsValue::from_str("name")).unwrap();
+    log(&format!("Name: {:?}", name.as_string()));
+
+    web_sys::console::log_1(&obj);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use js_sys::{Array, Function};
+    use wasm_bindgen_test::*;
+
+    use crate::matcher::{
+        check_array_equal, check_object_equal, check_object_match, check_pattern, check_value_equal,
+    };
+    use crate::parser::parse_pattern;
+    use crate::types::Pattern;
+    use crate::utils::ObjectWithProps;
+
+    #[wasm_bindgen_test]
+    fn test_pattern_enum() {
+        let p1 = Pattern::Wildcard;
+        let p2 = Pattern::Value(JsValue::from_str("test"));
+        match p1 {
+            Pattern::Wildcard => {}
+            _ => panic!("should be Pattern::Wildcard"),
+        };
+        match p2 {
+            Pattern::Value(v) => assert_eq!(v.as_string().unwrap(), "test"),
+            _ => panic!("should be Pattern::Value"),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_value_equal_strings() {
+        let a = JsValue::from_str("string");
+        let b = JsValue::from_str("string");
+        let c = JsValue::from_str("not string");
+        assert!(check_value_equal(&a, &b).unwrap_or(false));
+        assert!(!check_value_equal(&a, &c).unwrap_or(true));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_value_equal_booleans() {
+        let a = JsValue::from_bool(true);
+        let b = JsValue::from_bool(true);
+        let c = JsValue::from_bool(false);
+        assert!(check_value_equal(&a, &b).unwrap_or(false));
+        assert!(!check_value_equal(&a, &c).unwrap_or(true));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_value_equal_nulls_and_undefineds() {
+        let a = JsValue::null();
+        let b = JsValue::null();
+        let c = JsValue::undefined();
+        let d = JsValue::undefined();
+        assert!(check_value_equal(&a, &b).unwrap_or(false));
+        assert!(check_value_equal(&c, &d).unwrap_or(false));
+        assert!(!check_value_equal(&a, &c).unwrap_or(true));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_array_equal() {
+        use std::iter::FromIterator;
+        let a = Array::from_iter([1, 2, 3].map(JsValue::from));
+        let b = Array::from_iter([1, 2, 3].map(JsValue::from));
+        let c = Array::from_iter([1, 2, 4].map(JsValue::from));
+
+        assert!(check_array_equal(&a, &b).unwrap_or(false));
+        assert!(!check_array_equal(&a, &c).unwrap_or(true));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_object_equal() {
+        let a = Object::new();
+        Reflect::set(&a, &JsValue::from_str("key"), &JsValue::from_str("value")).unwrap();
+
+        let b = Object::new();
+        Reflect::set(&b, &JsValue::from_str("key"), &JsValue::from_str("value")).unwrap();
+
+        let c = Object::new();
+        Reflect::set(
+            &c,
+            &JsValue::from_str("key"),
+            &JsValue::from_str("different"),
+        )
+        .unwrap();
+
+        assert!(check_object_equal(&a, &b).unwrap_or(false));
+        assert!(!check_object_equal(&a, &c).unwrap_or(true));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_object_match_partial() {
+        let pattern = Object::new();
+        Reflect::set(&pattern, &JsValue::from_str("x"), &JsValue::from_str("1")).unwrap();
+
+        let data = Object::new();
+        Reflect::set(&data, &JsValue::from_str("x"), &JsValue::from_str("1")).unwrap();
+        Reflect::set(&data, &JsValue::from_str("y"), &JsValue::from_str("1")).unwrap();
+
+        assert!(check_object_match(&data, &pattern).unwrap_or(false));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_object_match_nested() {
+        let inner_data = Object::new();
+        Reflect::set(
+            &inner_data,
+            &JsValue::from_str("x"),
+            &JsValue::from_str("1"),
+        )
+        .unwrap();
+        Reflect::set(
+            &inner_data,
+            &JsValue::from_str("y"),
+            &JsValue::from_str("1"),
+        )
+        .unwrap();
+
+        let data = Object::new();
+        Reflect::set(&data, &JsValue::from_str("inner"), &inner_data).unwrap();
+
+        let inner_pattern = Object::new();
+        Reflect::set(
+            &inner_pattern,
+            &JsValue::from_str("x"),
+            &JsValue::from_str("1"),
+        )
+        .unwrap();
+
+        let pattern = Object::new();
+        Reflect::set(&pattern, &JsValue::from_str("inner"), &inner_pattern).unwrap();
+
+        assert!(check_object_match(&data, &pattern).unwrap_or(false));
+        assert!(!check_object_match(&pattern, &data).unwrap_or(true));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_function_match() {
+        let big_data = JsValue::from_f64(10.0);
+        let small_data = JsValue::from_f64(1.0);
+        let func = Function::new_with_args("x", "return x > 5");
+
+        assert!(check_pattern(&big_data, &Pattern::Function(func.clone().into())).unwrap_or(false));
+        assert!(!check_pattern(&small_data, &Pattern::Function(func.into())).unwrap_or(true));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_check_pattern_value() {
+        let data = JsValue::from_f64(1.0);
+        let pattern_same = Pattern::Value(JsValue::from_f64(1.0));
+        let pattern_diff = Pattern::Value(JsValue::from_f64(2.0));
+
+        assert!(check_pattern(&data, &pattern_same).unwrap_or(false));
+        assert!(!check_pattern(&data, &pattern_diff).unwrap_or(true));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_check_pattern_wildcard() {
+        let data = JsValue::from_f64(1.0);
+        let pattern = Pattern::Wildcard;
+
+        assert!(check_pattern(&data, &pattern).unwrap_or(false));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_partern_value() {
+        let pattern_obj = Object::new();
+        Reflect::set(
+            &pattern_obj,
+            JsValue::from_str("type").as_ref(),
+            &JsValue::from_str("Value"),
+        )
+        .unwrap();
+        Reflect::set(
+            &pattern_obj,
+            &JsValue::from_str("value"),
+            &JsValue::from_f64(1.0),
+        )
+        .unwrap();
+
+        let res = parse_pattern(&pattern_obj).unwrap();
+        assert_eq!(res, Pattern::Value(JsValue::from_f64(1.0)));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_pattern_wildcard() {
+        let pattern_obj = Object::new();
+        Reflect::set(
+            &pattern_obj,
+            JsValue::from_str("type").as_ref(),
+            &JsValue::from_str("Wildcard"),
+        )
+        .unwrap();
+
+        let res = parse_pattern(&pattern_obj).unwrap();
+        assert_eq!(res, Pattern::Wildcard);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_full_match() {
+        let patterns = Array::from_iter([
+            Object::new()
+                .with_prop("type", "Value")
+                .with_prop("value", JsValue::from_f64(1.0)),
+            Object::new().with_prop("type", "Wildcard"),
+        ]);
+
+        let data_matched = JsValue::from_f64(1.0);
+        let data_default = JsValue::from_f64(0.0);
+        let result_matched = match_pattern(data_matched, patterns.clone().into()).unwrap();
+        let result_default = match_pattern(data_default, patterns.clone().into()).unwrap();
+        assert_eq!(result_matched.as_f64().unwrap(), 0.0);
+        assert_eq!(result_default.as_f64().unwrap(), 1.0);
+    }
+}
@@ -1,6 +1,7 @@
+use js_sys::Reflect;
 use js_sys::{Array, Object};
 use match_pattern_rs::{ObjectWithProps, compile_match_plan, match_pattern};
-use wasm_bindgen::JsValue;
+use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
 
 wasm_bindgen_test_configure!(run_in_browser);
@@ -13,26 +14,31 @@ fn browser_compile_match_plan_reports_shadowed_branch() {
             Object::new()
                 .with_prop("actionIndex", 0)
                 .with_prop("predicate", Object::new().with_prop("kind", "isNumber")),
-            Object::new()
-                .with_prop("actionIndex", 1)
-                .with_prop(
-                    "predicate",
-                    Object::new()
-                        .with_prop("kind", "eq")
-                        .with_prop("value", JsValue::from_f64(1.0)),
-                ),
+            Object::new().with_prop("actionIndex", 1).with_prop(
+                "predicate",
+                Object::new()
+                    .with_prop("kind", "eq")
+                    .with_prop("value", JsValue::from_f64(1.0)),
+            ),
         ]),
     );
 
-    let plan =
-        serde_wasm_bindgen::from_value::<serde_json::Value>(compile_match_plan(program.into()).unwrap())
-            .unwrap();
+    let plan = serde_wasm_bindgen::from_value::<serde_json::Value>(
+        compile_match_plan(program.into()).unwrap(),
+    )
+    .unwrap();
 
-    assert_eq!(plan["version"], 1);
+    assert_eq!(plan["version"], 2);
     assert_eq!(plan["branches"].as_array().unwrap().len(), 2);
-    assert!(plan["diagnostics"].as_array().unwrap().iter().any(|diagnostic| {
-        diagnostic["code"] == "unreachable_branch" && diagnostic["branchIndex"] == 1
-    }));
+    assert!(
+        plan["diagnostics"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|diagnostic| {
+                diagnostic["code"] == "unreachable_branch" && diagnostic["branchIndex"] == 1
+            })
+    );
 }
 
 #[wasm_bindgen_test]
@@ -56,3 +62,29 @@ fn browser_match_pattern_keeps_branch_order() {
     assert_eq!(exact_match, 0.0);
     assert_eq!(fallback_match, 1.0);
 }
+
+#[wasm_bindgen_test]
+fn browser_errors_carry_code_and_path() {
+    let patterns = Array::from_iter([
+        Object::new().with_prop("type", "Wildcard"),
+        Object::new().with_prop("type", "Regex"),
+    ]);
+
+    let error: JsValue = match_pattern(JsValue::NULL, patterns.into())
+        .unwrap_err()
+        .into();
+    let path = Reflect::get(&error, &JsValue::from_str("path")).unwrap();
+
+    assert!(error.is_instance_of::<js_sys::Error>());
+    assert_eq!(
+        Reflect::get(&error, &JsValue::from_str("code")).unwrap(),
+        "unknown_pattern_type"
+    );
+    assert_eq!(
+        Array::from(&path)
+            .iter()
+            .map(|segment| segment.as_string().unwrap())
+            .collect::<Vec<_>>(),
+        ["1", "type"]
+    );
+}
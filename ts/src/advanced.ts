@@ -12,4 +12,5 @@ export type {
   CompiledPredicate,
   MatchValue,
   PredicateAst,
+  RelatedLocation,
 } from "./internal/ast";
@@ -41,7 +41,7 @@ const engine = createMatchEngine({
   compile: compileViaWasmOrJs,
   fallbackCompile: compileProgramInJs,
   compilerVersion: () =>
-    wasmModule?.compile_match_plan ? "rust-compiler-v1" : "js-compiler-v1",
+    wasmModule?.compile_match_plan ? "rust-compiler-v2" : "js-compiler-v1",
   slotRegistry: getDefaultSlotRegistry(),
 });
 
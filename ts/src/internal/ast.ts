@@ -28,18 +28,46 @@ export type CompiledPredicate =
   | { kind: "isUndefined" }
   | { kind: "eq"; value: MatchValue }
   | { kind: "tagEq"; key: string; value: MatchValue }
+  | { kind: "oneOf"; values: MatchValue[] }
+  | { kind: "startsWith"; value: string }
+  | { kind: "endsWith"; value: string }
+  | { kind: "contains"; value: string }
+  | { kind: "regex"; pattern: string; flags?: string }
+  | {
+      kind: "range";
+      min?: number;
+      max?: number;
+      minInclusive?: boolean;
+      maxInclusive?: boolean;
+    }
   | { kind: "shape"; fields: Record<string, CompiledPredicate>; exact: boolean }
+  | { kind: "tuple"; items: CompiledPredicate[]; rest?: CompiledPredicate }
+  | { kind: "arrayOf"; predicate: CompiledPredicate }
+  | { kind: "arrayLength"; length: number }
   | { kind: "and"; predicates: CompiledPredicate[] }
   | { kind: "or"; predicates: CompiledPredicate[] }
   | { kind: "not"; predicate: CompiledPredicate }
   | { kind: "slot"; slot: number }
   | { kind: "true" };
 
+// `path` leads into the JSON form of a branch's `predicate`; `[]` is the whole predicate.
+export type RelatedLocation = {
+  branchIndex: number;
+  path: string[];
+  message: string;
+};
+
 export type CompileDiagnostic = {
   code: string;
   level: "info" | "warning" | "error";
   message: string;
-  branchIndex: number;
+  branchIndex?: number;
+  counterexample?: MatchValue;
+  // Set when the counterexample is `undefined`, which JSON cannot hold.
+  counterexampleUndefined?: boolean;
+  path?: string[];
+  shadowedBy?: number;
+  related?: RelatedLocation[];
 };
 
 export type CompileBranch = {
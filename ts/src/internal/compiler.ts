@@ -9,7 +9,9 @@ import type {
 } from "./ast";
 import { deepEqual } from "./object";
 
-const COMPILE_PLAN_VERSION = 1;
+// Matches the Rust compiler's `COMPILE_PLAN_VERSION`; this compiler only emits the
+// version 1 predicate kinds, which version 2 still accepts.
+const COMPILE_PLAN_VERSION = 2;
 type TypeOfValue = Extract<CompiledPredicate, { kind: "typeOf" }>["value"];
 
 const assertNever = (value: never): never => {
@@ -7,6 +7,38 @@ const assertNever = (value: never): never => {
 };
 type RuntimeValue = string | number | boolean | object | null | undefined;
 type TypeOfValue = Extract<CompiledPredicate, { kind: "typeOf" }>["value"];
+type RangePredicate = Extract<CompiledPredicate, { kind: "range" }>;
+
+// `g` and `y` make `test` stateful, so they are dropped like the Rust evaluator does.
+// An invalid pattern is cached as `null` and never matches.
+const regexCache = new Map<string, RegExp | null>();
+const compileRegex = (pattern: string, flags = ""): RegExp | null => {
+  const key = `${flags}/${pattern}`;
+  if (!regexCache.has(key)) {
+    let regex: RegExp | null = null;
+    try {
+      regex = new RegExp(pattern, flags.replace(/[gy]/g, ""));
+    } catch {
+      regex = null;
+    }
+    regexCache.set(key, regex);
+  }
+  return regexCache.get(key) ?? null;
+};
+
+const inRange = (predicate: RangePredicate, value: number): boolean => {
+  if (predicate.min !== undefined) {
+    if (predicate.minInclusive ? value < predicate.min : value <= predicate.min) {
+      return false;
+    }
+  }
+  if (predicate.max !== undefined) {
+    if (predicate.maxInclusive ? value > predicate.max : value >= predicate.max) {
+      return false;
+    }
+  }
+  return true;
+};
 
 const matchesType = (
   typeName: TypeOfValue,
@@ -45,6 +77,21 @@ const evaluateCompiledPredicate = (
         isPlainObject(value) &&
         deepEqual((value as PlainObject)[predicate.key], predicate.value)
       );
+    case "oneOf":
+      return predicate.values.some((item) => deepEqual(value, item));
+    case "startsWith":
+      return typeof value === "string" && value.startsWith(predicate.value);
+    case "endsWith":
+      return typeof value === "string" && value.endsWith(predicate.value);
+    case "contains":
+      return typeof value === "string" && value.includes(predicate.value);
+    case "regex":
+      return (
+        typeof value === "string" &&
+        (compileRegex(predicate.pattern, predicate.flags)?.test(value) ?? false)
+      );
+    case "range":
+      return typeof value === "number" && inRange(predicate, value);
     case "shape": {
       if (!isPlainObject(value)) {
         return false;
@@ -59,6 +106,34 @@ const evaluateCompiledPredicate = (
       }
       return predicate.exact ? Object.keys(source).length === fields.length : true;
     }
+    case "tuple": {
+      if (!Array.isArray(value)) {
+        return false;
+      }
+      const { items, rest } = predicate;
+      const lengthMatches = rest
+        ? value.length >= items.length
+        : value.length === items.length;
+      return (
+        lengthMatches &&
+        items.every((item, index) =>
+          evaluateCompiledPredicate(value[index], item, slots),
+        ) &&
+        (!rest ||
+          value
+            .slice(items.length)
+            .every((item) => evaluateCompiledPredicate(item, rest, slots)))
+      );
+    }
+    case "arrayOf":
+      return (
+        Array.isArray(value) &&
+        value.every((item) =>
+          evaluateCompiledPredicate(item, predicate.predicate, slots),
+        )
+      );
+    case "arrayLength":
+      return Array.isArray(value) && value.length === predicate.length;
     case "and":
       return predicate.predicates.every((item) =>
         evaluateCompiledPredicate(value, item, slots),
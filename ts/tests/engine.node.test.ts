@@ -1,6 +1,7 @@
 import { describe, expect, it, vi } from "vitest";
 
 import { compileProgramInJs, createMatchEngine } from "../src/advanced";
+import type { CompilePlan } from "../src/advanced";
 import {
   and,
   eq,
@@ -320,4 +321,66 @@ describe("engine compile pipeline", () => {
       "No match found",
     );
   });
+  it("runs version 2 predicate kinds from plans compiled elsewhere", () => {
+    const plan: CompilePlan = {
+      version: 2,
+      branches: [
+        {
+          actionIndex: 0,
+          predicate: {
+            kind: "tuple",
+            items: [{ kind: "eq", value: "pay" }],
+            rest: { kind: "range", min: 0, minInclusive: true },
+          },
+        },
+        {
+          actionIndex: 1,
+          predicate: {
+            kind: "arrayOf",
+            predicate: { kind: "oneOf", values: ["a", "b"] },
+          },
+        },
+        {
+          actionIndex: 2,
+          predicate: {
+            kind: "and",
+            predicates: [
+              { kind: "startsWith", value: "order." },
+              { kind: "regex", pattern: "PAID$", flags: "ig" },
+            ],
+          },
+        },
+        { actionIndex: 3, predicate: { kind: "arrayLength", length: 2 } },
+        { actionIndex: 4, predicate: { kind: "true" } },
+      ],
+      diagnostics: [],
+      dynamicSlotCount: 0,
+    };
+    const engine = createMatchEngine({
+      compile: () => plan,
+      compilerVersion: "precompiled-v2",
+    });
+
+    const run = (input: string | number | (string | number)[]) =>
+      engine
+        .match(input)
+        .when(1)
+        .to("tuple")
+        .when(2)
+        .to("arrayOf")
+        .when(3)
+        .to("string")
+        .when(4)
+        .to("length")
+        .otherwise("other");
+
+    expect(run(["pay", 0, 2.5])).toBe("tuple");
+    expect(run(["pay", -1])).toBe("length");
+    expect(run(["a", "b", "a"])).toBe("arrayOf");
+    expect(run([])).toBe("arrayOf");
+    expect(run("order.paid")).toBe("string");
+    expect(run("order.paid")).toBe("string");
+    expect(run("order.open")).toBe("other");
+    expect(run(5)).toBe("other");
+  });
 });